@@ -0,0 +1,49 @@
+#!/bin/sh
+# Regenerates the theme screenshots and the sample animation in the README,
+# all showing the same time. Needs the fonts named in each theme installed.
+set -e
+cd "$(dirname "$0")/.."
+cargo build --release
+
+# Runs the clock headless with `$theme` as its only configuration.
+with_theme() {
+    home=$(mktemp -d)
+    mkdir -p "$home/.config/flipclock"
+    printf '%s\n' "$theme" > "$home/.config/flipclock/config.toml"
+    HOME="$home" ./target/release/flipclock-rs --backend headless "$@"
+    rm -r "$home"
+}
+
+theme='[theme]
+background_color = "#1E1E2E"
+card_color = "#313244"
+card_border_color = "#CBA6F7"
+card_border_size = 2
+card_rounded_corners = true
+card_gap = 8
+number_color = "#CDD6F4"
+font_path = "/usr/share/fonts/TTF/JetBrainsMono-Bold.ttf"'
+with_theme --at 10:08:30 --screenshot docs/mocha-screenshot.png
+with_theme --from 12:59:55 --duration 10s --record docs/mocha-sample.gif
+
+theme='[theme]
+background_color = "#2E3440"
+card_color = "#3B4252"
+card_border_color = "#88C0D0"
+card_border_size = 2
+card_rounded_corners = true
+card_gap = 6
+number_color = "#ECEFF4"
+font_path = "/usr/share/fonts/TTF/InputMonoNerdFont-Regular.ttf"'
+with_theme --at 10:08:30 --screenshot docs/nord-screenshot.png
+
+theme='[theme]
+background_color = "#eff1f5"
+card_color = "#d1d2db"
+card_border_color = "#babbf1"
+card_border_size = 2
+card_rounded_corners = true
+card_gap = 7
+number_color = "#4c4f69"
+font_path = "/usr/share/fonts/TTF/CaskaydiaCoveNerdFont-Regular.ttf"'
+with_theme --at 10:08:30 --screenshot docs/latte-screenshot.png
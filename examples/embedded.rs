@@ -0,0 +1,45 @@
+//! Embeds the clock in a window owned by the application: it creates the
+//! canvas, polls SDL and decides when to quit, the clock only draws.
+//!
+//! cargo run --example embedded
+
+use flipclock::sdl2::event::Event;
+use flipclock::sdl2::keyboard::Keycode;
+use flipclock::{Config, FlipClock, Sdl2GraphicsEngine};
+use std::error::Error;
+use std::time::Duration;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let sdl_context = flipclock::sdl2::init()?;
+    let video_subsystem = sdl_context.video()?;
+    let ttf_context = flipclock::sdl2::ttf::init()?;
+    let mut event_pump = sdl_context.event_pump()?;
+
+    let window = video_subsystem
+        .window("Embedded flip clock", 800, 480)
+        .resizable()
+        .build()?;
+    let canvas = window.into_canvas().present_vsync().build()?;
+
+    let settings = Config::load()?.to_clock_settings();
+    let engine = Sdl2GraphicsEngine::with_canvas(canvas, &ttf_context, &settings)?;
+    let mut clock = FlipClock::new(engine, &settings);
+
+    loop {
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => return Ok(()),
+                // Everything else goes to the clock, so its keybindings keep working.
+                event => clock.engine_mut().push_event(event),
+            }
+        }
+        if clock.tick(&[])? {
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_millis(16));
+    }
+}
@@ -0,0 +1,198 @@
+use chrono::NaiveTime;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// What the clock is drawn with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// A window, or a screensaver host's window, through SDL.
+    #[default]
+    Sdl,
+    /// Half-block characters in the terminal.
+    Tui,
+    /// A Linux framebuffer device, for consoles without X11 or Wayland.
+    Framebuffer,
+    /// Nothing on screen, for screenshots without a display.
+    Headless,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "sdl" => Ok(Backend::Sdl),
+            "tui" => Ok(Backend::Tui),
+            "framebuffer" | "fb" => Ok(Backend::Framebuffer),
+            "headless" => Ok(Backend::Headless),
+            _ => Err(format!("Unknown backend: {}", name)),
+        }
+    }
+}
+
+/// Options given on the command line. Screensaver hosts such as XScreenSaver
+/// pass X11 style single dash flags, so `-root` and `--root` are both accepted.
+#[derive(Debug, Default)]
+pub struct CliOptions {
+    pub backend: Backend,
+    pub window_id: Option<u64>,
+    pub root: bool,
+    pub print_xscreensaver_config: bool,
+    pub print_desktop_entry: bool,
+    /// Actions to send to an already running clock instead of starting one.
+    pub send: Vec<String>,
+    /// Save a single frame here and exit instead of running the clock.
+    pub screenshot: Option<PathBuf>,
+    /// Like `screenshot`, but always drawn headless.
+    pub wallpaper: Option<PathBuf>,
+    /// Size of a headless image, the window size otherwise.
+    pub size: Option<(u32, u32)>,
+    /// Draw the digits of an SVG image as paths instead of text.
+    pub outline_text: bool,
+    /// Show this time instead of the current one.
+    pub at: Option<NaiveTime>,
+    /// Record an animation here and exit instead of running the clock.
+    pub record: Option<PathBuf>,
+    /// Time of day the recording starts at.
+    pub from: Option<NaiveTime>,
+    /// Length of the recording.
+    pub duration: Option<Duration>,
+}
+
+impl CliOptions {
+    /// Where to save a single frame, from `--screenshot` or `--wallpaper`.
+    pub fn image_path(&self) -> Option<&Path> {
+        self.screenshot.as_deref().or(self.wallpaper.as_deref())
+    }
+
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut options = CliOptions::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let flag = arg
+                .strip_prefix("--")
+                .or_else(|| arg.strip_prefix('-'))
+                .ok_or_else(|| format!("Unexpected argument: {}", arg))?;
+
+            match flag {
+                "backend" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("Missing value for {}", arg))?;
+                    options.backend = value.parse()?;
+                }
+                "window-id" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("Missing value for {}", arg))?;
+                    options.window_id = Some(parse_window_id(&value)?);
+                }
+                "root" => options.root = true,
+                "xscreensaver-config" => options.print_xscreensaver_config = true,
+                "desktop-entry" => options.print_desktop_entry = true,
+                "send" => {
+                    let action = args
+                        .next()
+                        .ok_or_else(|| format!("Missing value for {}", arg))?;
+                    options.send.push(action);
+                }
+                "screenshot" => {
+                    let path = args
+                        .next()
+                        .ok_or_else(|| format!("Missing value for {}", arg))?;
+                    options.screenshot = Some(PathBuf::from(path));
+                }
+                "wallpaper" => {
+                    let path = args
+                        .next()
+                        .ok_or_else(|| format!("Missing value for {}", arg))?;
+                    options.wallpaper = Some(PathBuf::from(path));
+                }
+                "size" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("Missing value for {}", arg))?;
+                    options.size = Some(parse_size(&value)?);
+                }
+                "outline-text" => options.outline_text = true,
+                "at" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("Missing value for {}", arg))?;
+                    options.at = Some(parse_time(&value)?);
+                }
+                "record" => {
+                    let path = args
+                        .next()
+                        .ok_or_else(|| format!("Missing value for {}", arg))?;
+                    options.record = Some(PathBuf::from(path));
+                }
+                "from" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("Missing value for {}", arg))?;
+                    options.from = Some(parse_time(&value)?);
+                }
+                "duration" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("Missing value for {}", arg))?;
+                    options.duration = Some(parse_duration(&value)?);
+                }
+                _ => return Err(format!("Unknown option: {}", arg)),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// X11 window ids are handed around in hex (`0x1a00007`) as often as in decimal.
+pub fn parse_window_id(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let parsed = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    parsed.map_err(|_| format!("Invalid window id: {}", value))
+}
+
+/// A time of day as `HH:MM:SS` or `HH:MM`.
+pub fn parse_time(value: &str) -> Result<NaiveTime, String> {
+    let value = value.trim();
+    NaiveTime::parse_from_str(value, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M"))
+        .map_err(|_| format!("Invalid time: {}", value))
+}
+
+/// An image size as `WIDTHxHEIGHT`, e.g. `3840x2160`.
+pub fn parse_size(value: &str) -> Result<(u32, u32), String> {
+    let value = value.trim();
+    value
+        .split_once(['x', 'X'])
+        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+        .filter(|&(width, height)| width > 0 && height > 0)
+        .ok_or_else(|| format!("Invalid size: {}", value))
+}
+
+/// A duration such as `10s`, `1500ms` or `2m`, in seconds without a unit.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let invalid = || format!("Invalid duration: {}", value);
+    let (number, unit) = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .map_or((value, ""), |index| value.split_at(index));
+    let number: f64 = number.parse().map_err(|_| invalid())?;
+    let seconds = match unit.trim() {
+        "" | "s" => number,
+        "ms" => number / 1000.0,
+        "m" | "min" => number * 60.0,
+        _ => return Err(invalid()),
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|_| invalid())
+}
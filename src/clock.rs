@@ -1,16 +1,186 @@
-use crate::config::ClockSettings;
-use crate::graphics_engine::{
-    AnimationState, GraphicsEngine, TimeDigitPosition,
-};
-use chrono::{Local, Timelike};
+use crate::config::{Action, AnimationStyle, ClockSettings};
+use crate::error::FlipClockError;
+use crate::graphics_engine::{AnimationState, GraphicsEngine, Rect, TimeDigitPosition};
+use crate::ipc::ControlSocket;
+use crate::recording::{Recording, RecordingFormat};
+use chrono::{Local, NaiveDate, NaiveTime, TimeDelta, Timelike, Utc};
+use chrono_tz::Tz;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
+/// How often the time is checked for changes.
+const UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+/// Delay between frames, roughly 60 fps.
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+/// Delay between recorded frames. GIF delays are in centiseconds and viewers
+/// slow down anything shorter than two, so 25 fps.
+const RECORDING_FRAME_INTERVAL: Duration = Duration::from_millis(40);
+const DIM_STEP: f32 = 0.1;
+/// Never dim all the way to black, the clock should stay readable.
+const MAX_DIM_LEVEL: f32 = 0.9;
+
+/// A stopwatch shown on the cards in place of the time of day. A single
+/// action cycles it through start, stop and back to the clock.
+enum Timer {
+    Idle,
+    Running(Instant),
+    Stopped(Duration),
+}
+
+/// Where the clock gets the time of day from, and the time animations run on.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum TimeSource {
+    #[default]
+    System,
+    /// Always the same time, for reproducible screenshots.
+    Fixed(NaiveTime),
+    /// Starts at `start` and only moves on when advanced, for recordings.
+    Simulated {
+        start: NaiveTime,
+        epoch: Instant,
+        elapsed: Duration,
+    },
+}
+
+impl TimeSource {
+    fn simulated(start: NaiveTime) -> Self {
+        TimeSource::Simulated {
+            start,
+            epoch: Instant::now(),
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    /// Stands in for `Instant::now()` wherever animations are timed.
+    fn now(&self) -> Instant {
+        match self {
+            TimeSource::Simulated { epoch, elapsed, .. } => *epoch + *elapsed,
+            _ => Instant::now(),
+        }
+    }
+
+    fn time_of_day(&self, time_zone: Option<Tz>) -> NaiveTime {
+        match (self, time_zone) {
+            (TimeSource::Fixed(time), _) => *time,
+            (TimeSource::Simulated { start, elapsed, .. }, _) => {
+                let elapsed = TimeDelta::from_std(*elapsed).unwrap_or(TimeDelta::zero());
+                start.overflowing_add_signed(elapsed).0
+            }
+            (TimeSource::System, Some(time_zone)) => Utc::now().with_timezone(&time_zone).time(),
+            (TimeSource::System, None) => Local::now().time(),
+        }
+    }
+
+    /// Today, also for fixed and simulated times, which only set the time of day.
+    fn date(&self, time_zone: Option<Tz>) -> NaiveDate {
+        match time_zone {
+            Some(time_zone) => Utc::now().with_timezone(&time_zone).date_naive(),
+            None => Local::now().date_naive(),
+        }
+    }
+
+    fn advance(&mut self, step: Duration) {
+        if let TimeSource::Simulated { elapsed, .. } = self {
+            *elapsed += step;
+        }
+    }
+}
+
+/// Where the cards were placed, to slide them when the layout changes.
+#[derive(Clone, Copy, PartialEq)]
+struct CardRects {
+    hour: Rect,
+    minute: Rect,
+    second: Option<Rect>,
+}
+
+/// The animation timeline of a single card during one update.
+struct FieldTimeline {
+    position: TimeDigitPosition,
+    current_value: u32,
+    previous_value: Option<u32>,
+    rect: Rect,
+    /// Where the card slides in from when the layout changed, `rect` otherwise.
+    start_rect: Rect,
+    /// Card that is no longer part of the layout and slides away behind its neighbour.
+    leaving: bool,
+    delay: Duration,
+    duration: Duration,
+}
+
+impl FieldTimeline {
+    fn new(
+        position: TimeDigitPosition,
+        current_value: u32,
+        previous_value: Option<u32>,
+        rect: Rect,
+    ) -> Self {
+        FieldTimeline {
+            position,
+            current_value,
+            previous_value,
+            rect,
+            start_rect: rect,
+            leaving: false,
+            delay: Duration::ZERO,
+            duration: Duration::ZERO,
+        }
+    }
+
+    fn sliding_from(mut self, start_rect: Option<Rect>) -> Self {
+        self.start_rect = start_rect.unwrap_or(self.rect);
+        self
+    }
+
+    fn rect_at(&self, layout_progress: f32) -> Rect {
+        self.start_rect.lerp(&self.rect, layout_progress)
+    }
+
+    fn is_changed(&self) -> bool {
+        self.previous_value != Some(self.current_value)
+    }
+
+    /// Progress of this card's own animation, which only starts once its delay has passed.
+    fn progress(&self, elapsed: Duration) -> f32 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+        elapsed.saturating_sub(self.delay).as_secs_f32() / self.duration.as_secs_f32()
+    }
+}
+
+/// A card update that is still being animated, advanced one frame per tick so
+/// that several clocks can animate side by side.
+struct Update {
+    fields: Vec<FieldTimeline>,
+    started: Instant,
+    animate_digits: bool,
+    layout_duration: Duration,
+    total_duration: Duration,
+    am_pm: Option<bool>,
+    /// The date card and what it reads.
+    date: Option<(Rect, String)>,
+}
+
 pub struct FlipClock<E: GraphicsEngine> {
     engine: E,
     settings: ClockSettings,
     past_hour: Option<u32>,
     past_minute: Option<u32>,
     past_second: Option<u32>,
+    paused: bool,
+    timer: Timer,
+    card_rects: Option<CardRects>,
+    update: Option<Update>,
+    /// The last update once it is over, to draw its frame again for a screenshot.
+    finished_update: Option<Update>,
+    last_update: Option<Instant>,
+    time_source: TimeSource,
+    /// Where to save the next frame, taken with the screenshot action.
+    screenshot: Option<PathBuf>,
+    recording: Option<Recording>,
+    control_socket: Option<ControlSocket>,
 }
 
 impl<E: GraphicsEngine> FlipClock<E> {
@@ -21,12 +191,65 @@ impl<E: GraphicsEngine> FlipClock<E> {
             past_hour: None,
             past_minute: None,
             past_second: None,
+            paused: false,
+            timer: Timer::Idle,
+            card_rects: None,
+            update: None,
+            finished_update: None,
+            last_update: None,
+            time_source: TimeSource::System,
+            screenshot: None,
+            recording: None,
+            control_socket: None,
+        }
+    }
+
+    /// Also accept actions sent by other processes through `socket`.
+    pub fn with_control_socket(mut self, socket: ControlSocket) -> Self {
+        self.set_control_socket(socket);
+        self
+    }
+
+    /// Like `with_control_socket`, for a clock that is already in place.
+    pub fn set_control_socket(&mut self, socket: ControlSocket) {
+        self.control_socket = Some(socket);
+    }
+
+    pub fn with_time_source(mut self, time_source: TimeSource) -> Self {
+        self.time_source = time_source;
+        self
+    }
+
+    pub fn engine(&self) -> &E {
+        &self.engine
+    }
+
+    pub fn engine_mut(&mut self) -> &mut E {
+        &mut self.engine
+    }
+
+    fn timer_elapsed(&self) -> Option<Duration> {
+        match self.timer {
+            Timer::Idle => None,
+            Timer::Running(started) => Some(started.elapsed()),
+            Timer::Stopped(elapsed) => Some(elapsed),
         }
     }
 
     fn get_current_time(&self) -> (u32, u32, u32, bool) {
-        let time = Local::now();
+        if let Some(elapsed) = self.timer_elapsed() {
+            let seconds = elapsed.as_secs();
+            let hour = (seconds / 3600 % 100) as u32;
+            return (
+                hour,
+                (seconds / 60 % 60) as u32,
+                (seconds % 60) as u32,
+                false,
+            );
+        }
+
         let settings = self.engine.get_settings();
+        let time = self.time_source.time_of_day(settings.time_zone);
 
         let (hour, am_pm) = if settings.use_24hour {
             (time.hour(), false)
@@ -40,138 +263,569 @@ impl<E: GraphicsEngine> FlipClock<E> {
 
         (hour, minute, second, am_pm)
     }
-    fn render(&mut self) -> Result<(), String> {
-        let (hour, minute, second, is_pm) = self.get_current_time();
+    fn field_range(&self, position: &TimeDigitPosition) -> (u32, u32) {
+        match position {
+            TimeDigitPosition::Hour if self.timer_elapsed().is_some() => (0, 99),
+            TimeDigitPosition::Hour if self.engine.get_settings().use_24hour => (0, 23),
+            TimeDigitPosition::Hour => (1, 12),
+            TimeDigitPosition::Minute | TimeDigitPosition::Second => (0, 59),
+        }
+    }
+
+    /// Values strictly between `from` and `to`, counting forward and wrapping
+    /// around the range of the field.
+    fn intermediate_values(&self, position: &TimeDigitPosition, from: u32, to: u32) -> Vec<u32> {
+        let (min, max) = self.field_range(position);
+        let next = |value: u32| if value >= max { min } else { value + 1 };
+
+        let mut values = Vec::new();
+        let mut value = next(from);
+        while value != to && values.len() <= (max - min) as usize {
+            values.push(value);
+            value = next(value);
+        }
+        values
+    }
+
+    /// With split-flap enabled a card with no history starts from the first
+    /// value of its drum, so the whole board rattles into place on start.
+    fn previous_value(&self, position: &TimeDigitPosition, past: Option<u32>) -> Option<u32> {
+        if self.engine.get_settings().split_flap {
+            past.or(Some(self.field_range(position).0))
+        } else {
+            past
+        }
+    }
+
+    /// How long a card takes to reach its new value. A split-flap card gives
+    /// every flap its own interval instead of squeezing them into `duration`,
+    /// and a card that keeps its value does not flip at all.
+    fn field_duration(&self, field: &FieldTimeline, duration: Duration) -> Duration {
         let settings = self.engine.get_settings();
-        let animate_flip = settings.animate_flip;
-        let animation_duration_ms = settings.animation_duration_ms;
-        let show_seconds = settings.show_seconds;
+        match field.previous_value {
+            _ if !field.is_changed() => Duration::ZERO,
+            Some(previous) if settings.split_flap => {
+                let flaps = self
+                    .intermediate_values(&field.position, previous, field.current_value)
+                    .len() as u32
+                    + 1;
+                Duration::from_millis(settings.flap_interval_ms as u64) * flaps
+            }
+            _ => duration,
+        }
+    }
 
-        let layout = self.engine.calculate_layout();
-        let hour_rect = layout.hour_rect;
-        let minute_rect = layout.minute_rect;
-        let second_rect_option = layout.second_rect;
-
-        let is_hour_changed = self.past_hour.map_or(true, |past| past != hour);
-        let is_minute_changed = self.past_minute.map_or(true, |past| past != minute);
-        let is_second_changed = self.past_second.map_or(true, |past| past != second);
-
-        self.engine.clear()?; // Clear the entire canvas at the beginning of each full render cycle
-
-        if animate_flip {
-            let duration = Duration::from_millis(animation_duration_ms as u64);
-            let animation_start_time = Instant::now(); // Start time for the entire animation sequence
-
-            while animation_start_time.elapsed() < duration {
-                let current_animation_progress =
-                    animation_start_time.elapsed().as_secs_f32() / duration.as_secs_f32();
-
-                self.engine.clear()?; // Clear for each frame of the animation
-
-                // Render hour
-                self.engine.render_digit(
-                    hour,
-                    TimeDigitPosition::Hour,
-                    &hour_rect,
-                    if is_hour_changed {
-                        Some(AnimationState {
-                            current_value: hour,
-                            previous_value: self.past_hour,
-                            is_animating: true,
-                            progress: current_animation_progress.clamp(0.0, 1.0),
-                        })
-                    } else {
-                        None // No animation needed
-                    },
-                )?;
-
-                // Render minute
-                self.engine.render_digit(
-                    minute,
-                    TimeDigitPosition::Minute,
-                    &minute_rect,
-                    if is_minute_changed {
-                        Some(AnimationState {
-                            current_value: minute,
-                            previous_value: self.past_minute,
-                            is_animating: true,
-                            progress: current_animation_progress.clamp(0.0, 1.0),
-                        })
-                    } else {
-                        None // No animation needed
-                    },
-                )?;
-
-                // Render second
-                if show_seconds {
-                    if let Some(rect) = &second_rect_option {
-                        self.engine.render_digit(
-                            second,
-                            TimeDigitPosition::Second,
-                            rect,
-                            if is_second_changed {
-                                Some(AnimationState {
-                                    current_value: second,
-                                    previous_value: self.past_second,
-                                    is_animating: true,
-                                    progress: current_animation_progress.clamp(0.0, 1.0),
-                                })
-                            } else {
-                                None // No animation needed
-                            },
-                        )?;
-                    }
-                }
+    fn animation_state(
+        &self,
+        position: TimeDigitPosition,
+        current_value: u32,
+        previous_value: Option<u32>,
+        progress: f32,
+    ) -> AnimationState {
+        let settings = self.engine.get_settings();
+        let style = settings.animation_style;
+        let shows_intermediate_values =
+            settings.split_flap || matches!(style, AnimationStyle::Roll | AnimationStyle::Rattle);
+        let intermediate_values = match previous_value {
+            Some(previous) if shows_intermediate_values => {
+                self.intermediate_values(&position, previous, current_value)
+            }
+            _ => Vec::new(),
+        };
+
+        AnimationState {
+            style,
+            current_value,
+            previous_value,
+            intermediate_values,
+            is_animating: true,
+            progress: progress.clamp(0.0, 1.0),
+        }
+    }
+
+    fn render_field(
+        &mut self,
+        field: &FieldTimeline,
+        elapsed: Option<Duration>,
+        layout_progress: f32,
+    ) -> Result<(), FlipClockError> {
+        let animation = match elapsed {
+            Some(elapsed) if field.is_changed() => Some(self.animation_state(
+                field.position,
+                field.current_value,
+                field.previous_value,
+                field.progress(elapsed),
+            )),
+            _ => None, // No animation needed
+        };
 
-                self.engine.render_am_pm_indicator(&hour_rect, is_pm)?;
+        self.engine.render_digit(
+            field.current_value,
+            field.position,
+            &field.rect_at(layout_progress),
+            animation,
+        )
+    }
 
-                self.engine.present()?;
-                std::thread::sleep(Duration::from_millis(16));
+    fn render_frame(
+        &mut self,
+        fields: &[FieldTimeline],
+        elapsed: Option<Duration>,
+        layout_progress: f32,
+        am_pm: Option<bool>,
+        date: Option<&(Rect, String)>,
+    ) -> Result<(), FlipClockError> {
+        self.draw_frame(fields, elapsed, layout_progress, am_pm, date)?;
 
-                // If all changed flags are false, the animation is complete for this update
-                if !is_hour_changed && !is_minute_changed && !is_second_changed {
-                    break;
-                }
+        if let Some(path) = self.screenshot.take()
+            && let Err(e) = self.engine.save_screenshot(&path)
+        {
+            eprintln!("Failed to save screenshot to {}: {}", path.display(), e);
+        }
+        if let Some(recording) = &mut self.recording {
+            recording.push(&self.engine.read_pixels()?, self.time_source.now())?;
+        }
+        self.engine.present()
+    }
+
+    fn draw_frame(
+        &mut self,
+        fields: &[FieldTimeline],
+        elapsed: Option<Duration>,
+        layout_progress: f32,
+        am_pm: Option<bool>,
+        date: Option<&(Rect, String)>,
+    ) -> Result<(), FlipClockError> {
+        self.engine.clear()?;
+
+        if let Some((rect, text)) = date {
+            self.engine.render_date(rect, text)?;
+        }
+
+        // Seconds first, so a card sliding in or out stays behind the minutes.
+        for field in fields.iter().rev() {
+            if field.leaving && layout_progress >= 1.0 {
+                continue;
             }
-        } else {
-            // If no animation, just render the current state
-            self.engine.clear()?;
-            self.engine
-                .render_digit(hour, TimeDigitPosition::Hour, &hour_rect, None)?;
+            self.render_field(field, elapsed, layout_progress)?;
+        }
+
+        if let Some(is_pm) = am_pm {
+            let hour_rect = fields[0].rect_at(layout_progress);
+            let cards = fields
+                .iter()
+                .filter(|field| !(field.leaving && layout_progress >= 1.0))
+                .map(|field| field.rect_at(layout_progress))
+                .chain(date.map(|(rect, _)| *rect))
+                .fold(hour_rect, |cards, rect| cards.union(&rect));
             self.engine
-                .render_digit(minute, TimeDigitPosition::Minute, &minute_rect, None)?;
-            if show_seconds {
-                if let Some(rect) = &second_rect_option {
-                    self.engine
-                        .render_digit(second, TimeDigitPosition::Second, rect, None)?;
+                .render_am_pm_indicator(&hour_rect, &cards, is_pm)?;
+        }
+        Ok(())
+    }
+
+    fn render(&mut self) -> Result<(), FlipClockError> {
+        self.update = Some(self.next_update());
+        self.advance_update()
+    }
+
+    /// The update from what the cards show to the current time.
+    fn next_update(&mut self) -> Update {
+        let (hour, minute, second, is_pm) = self.get_current_time();
+        let settings = self.engine.get_settings();
+        let animate_flip =
+            settings.animate_flip && settings.animation_style != AnimationStyle::None;
+        let duration = Duration::from_millis(settings.animation_duration_ms as u64);
+        let stagger = Duration::from_millis(settings.animation_stagger_ms as u64);
+        let show_seconds = settings.show_seconds;
+        let am_pm = self.timer_elapsed().is_none().then_some(is_pm);
+        let date = self.timer_elapsed().is_none().then(|| {
+            let mut text = String::new();
+            let today = self.time_source.date(settings.time_zone);
+            // An invalid format shows up as an error while formatting.
+            if write!(text, "{}", today.format(&settings.date_format)).is_err() {
+                text = today.to_string();
+            }
+            text
+        });
+
+        let layout = self.engine.calculate_layout();
+        let card_rects = CardRects {
+            hour: layout.hour_rect,
+            minute: layout.minute_rect,
+            second: layout.second_rect.filter(|_| show_seconds),
+        };
+        let date = layout.date_rect.zip(date);
+        // After a toggle or a resize the cards slide from where they were drawn last.
+        let previous_rects = self
+            .card_rects
+            .replace(card_rects)
+            .filter(|previous| animate_flip && *previous != card_rects);
+
+        let mut fields = vec![
+            FieldTimeline::new(
+                TimeDigitPosition::Hour,
+                hour,
+                self.previous_value(&TimeDigitPosition::Hour, self.past_hour),
+                card_rects.hour,
+            )
+            .sliding_from(previous_rects.map(|previous| previous.hour)),
+            FieldTimeline::new(
+                TimeDigitPosition::Minute,
+                minute,
+                self.previous_value(&TimeDigitPosition::Minute, self.past_minute),
+                card_rects.minute,
+            )
+            .sliding_from(previous_rects.map(|previous| previous.minute)),
+        ];
+        match (card_rects.second, previous_rects) {
+            (Some(rect), _) => fields.push(
+                FieldTimeline::new(
+                    TimeDigitPosition::Second,
+                    second,
+                    self.previous_value(&TimeDigitPosition::Second, self.past_second),
+                    rect,
+                )
+                // A new seconds card comes out from behind the minutes.
+                .sliding_from(
+                    previous_rects.map(|previous| previous.second.unwrap_or(previous.minute)),
+                ),
+            ),
+            (
+                None,
+                Some(CardRects {
+                    second: Some(previous_rect),
+                    ..
+                }),
+            ) => {
+                if let Some(past_second) = self.past_second {
+                    let mut field = FieldTimeline::new(
+                        TimeDigitPosition::Second,
+                        past_second,
+                        Some(past_second),
+                        card_rects.minute,
+                    )
+                    .sliding_from(Some(previous_rect));
+                    field.leaving = true;
+                    fields.push(field);
                 }
             }
-            self.engine.render_am_pm_indicator(&hour_rect, is_pm)?;
-            self.engine.present()?;
+            (None, _) => {}
+        }
+        for field in fields.iter_mut() {
+            field.duration = self.field_duration(field, duration);
         }
 
+        // Like a mechanical carry: the fastest field flips first and every
+        // slower field that also changed follows one stagger step later.
+        let mut delay = Duration::ZERO;
+        for field in fields.iter_mut().rev().filter(|field| field.is_changed()) {
+            field.delay = delay;
+            delay += stagger;
+        }
+
+        let animate_digits = animate_flip && fields.iter().any(|field| field.is_changed());
+        let layout_duration = if previous_rects.is_some() {
+            duration
+        } else {
+            Duration::ZERO
+        };
+
+        let digits_duration = fields
+            .iter()
+            .filter(|_| animate_digits)
+            .map(|field| field.delay + field.duration)
+            .max()
+            .unwrap_or(Duration::ZERO);
+
         self.past_hour = Some(hour);
         self.past_minute = Some(minute);
         self.past_second = Some(second);
 
+        Update {
+            fields,
+            started: self.time_source.now(),
+            animate_digits,
+            layout_duration,
+            total_duration: digits_duration.max(layout_duration),
+            am_pm,
+            date,
+        }
+    }
+
+    /// Draws the next frame of the running update, and its last frame once it is over.
+    fn advance_update(&mut self) -> Result<(), FlipClockError> {
+        let Some(update) = self.update.take() else {
+            return Ok(());
+        };
+
+        let elapsed = self
+            .time_source
+            .now()
+            .saturating_duration_since(update.started)
+            .min(update.total_duration);
+        let layout_progress = if update.layout_duration.is_zero() {
+            1.0
+        } else {
+            smoothstep(elapsed.as_secs_f32() / update.layout_duration.as_secs_f32())
+        };
+        self.render_frame(
+            &update.fields,
+            update.animate_digits.then_some(elapsed),
+            layout_progress,
+            update.am_pm,
+            update.date.as_ref(),
+        )?;
+
+        if elapsed < update.total_duration {
+            self.update = Some(update);
+        } else {
+            self.finished_update = Some(update);
+        }
         Ok(())
     }
 
-    pub fn run(&mut self) -> Result<(), String> {
-        let mut last_update = Instant::now();
-        loop {
-            if self.engine.handle_events()? {
-                break;
+    /// Draws the current time once, as it looks after any animation, and
+    /// saves it to `path` without presenting it.
+    pub fn save_screenshot(&mut self, path: &Path) -> Result<(), FlipClockError> {
+        let update = self.next_update();
+        self.draw_frame(
+            &update.fields,
+            None,
+            1.0,
+            update.am_pm,
+            update.date.as_ref(),
+        )?;
+        self.engine.save_screenshot(path)
+    }
+
+    /// Applies an action, returning `true` when the clock should stop.
+    fn handle_action(&mut self, action: Action) -> Result<bool, FlipClockError> {
+        let mut settings = self.engine.get_settings().clone();
+
+        match action {
+            Action::Quit => return Ok(true),
+            Action::TogglePause => self.paused = !self.paused,
+            Action::StartStopTimer => {
+                self.timer = match self.timer {
+                    Timer::Idle => Timer::Running(Instant::now()),
+                    Timer::Running(started) => Timer::Stopped(started.elapsed()),
+                    Timer::Stopped(_) => Timer::Idle,
+                };
+            }
+            Action::Screenshot => self.screenshot = Some(screenshot_path()),
+            Action::ToggleFullscreen => settings.fullscreen = !settings.fullscreen,
+            Action::ToggleSeconds => settings.show_seconds = !settings.show_seconds,
+            Action::Toggle24Hour => settings.use_24hour = !settings.use_24hour,
+            Action::ToggleLeadingZero => settings.show_leading_zero = !settings.show_leading_zero,
+            Action::NextTheme => settings.select_theme(settings.theme_index as isize + 1),
+            Action::PreviousTheme => settings.select_theme(settings.theme_index as isize - 1),
+            Action::DimUp => {
+                settings.dim_level = (settings.dim_level + DIM_STEP).min(MAX_DIM_LEVEL)
             }
+            Action::DimDown => settings.dim_level = (settings.dim_level - DIM_STEP).max(0.0),
+            Action::Unbound => {}
+        }
+
+        self.engine.update_settings(settings)?;
+        Ok(false)
+    }
 
-            if last_update.elapsed() >= Duration::from_millis(250) {
-                self.render()?;
-                last_update = Instant::now();
+    /// Handles input and draws the next frame, returning `true` when the clock should stop.
+    /// `run_all` calls this in a loop, an application embedding the clock calls it once
+    /// per iteration of its own loop.
+    pub fn tick(&mut self, remote_actions: &[Action]) -> Result<bool, FlipClockError> {
+        let mut actions = self.engine.handle_events()?;
+        actions.extend_from_slice(remote_actions);
+
+        for action in actions {
+            if self.handle_action(action)? {
+                return Ok(true);
             }
+        }
+
+        self.draw_next_frame()?;
+        Ok(false)
+    }
 
-            std::thread::sleep(Duration::from_millis(16));
+    /// Advances a running update, or checks whether the time changed.
+    fn draw_next_frame(&mut self) -> Result<(), FlipClockError> {
+        if self.screenshot.is_some() && self.update.is_none() {
+            // Nothing is animating, draw the last frame again to capture it.
+            self.update = self.finished_update.take();
         }
 
+        let now = self.time_source.now();
+        if self.update.is_some() {
+            self.advance_update()?;
+        } else if !self.paused
+            && self.last_update.is_none_or(|last_update| {
+                now.saturating_duration_since(last_update) >= UPDATE_INTERVAL
+            })
+        {
+            self.last_update = Some(now);
+            self.render()?;
+        }
         Ok(())
     }
+
+    /// Runs the clock on simulated time from `from`, or the current time,
+    /// for `duration` and saves every frame drawn to `path` as an animated
+    /// GIF or PNG, depending on its extension.
+    pub fn record(
+        &mut self,
+        path: &Path,
+        from: Option<NaiveTime>,
+        duration: Duration,
+    ) -> Result<(), FlipClockError> {
+        let format = RecordingFormat::from_path(path)?;
+        let settings = self.engine.get_settings();
+        let from = from.unwrap_or_else(|| self.time_source.time_of_day(settings.time_zone));
+        self.time_source = TimeSource::simulated(from);
+        self.recording = Some(Recording::new(settings, self.time_source.now()));
+
+        let end = self.time_source.now() + duration;
+        while self.time_source.now() < end {
+            self.draw_next_frame()?;
+            self.time_source.advance(RECORDING_FRAME_INTERVAL);
+        }
+
+        match self.recording.take() {
+            Some(recording) => recording.save(path, format, end),
+            None => Ok(()),
+        }
+    }
+
+    /// Runs several clocks side by side, e.g. one per display, until any of
+    /// them is quit. Remote actions reach every clock.
+    pub fn run_all(clocks: &mut [Self]) -> Result<(), FlipClockError> {
+        loop {
+            let remote_actions: Vec<Action> = clocks
+                .iter_mut()
+                .filter_map(|clock| clock.control_socket.as_mut())
+                .flat_map(ControlSocket::poll)
+                .collect();
+
+            for clock in clocks.iter_mut() {
+                if clock.tick(&remote_actions)? {
+                    return Ok(());
+                }
+            }
+
+            std::thread::sleep(FRAME_INTERVAL);
+        }
+    }
+}
+
+fn smoothstep(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Screenshots go to the pictures directory, named after the time they were taken.
+fn screenshot_path() -> PathBuf {
+    let directory = dirs::picture_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_else(|| PathBuf::from("."));
+    directory.join(format!(
+        "flipclock-{}.png",
+        Local::now().format("%Y%m%d-%H%M%S")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics_engine::{ClockLayout, Color};
+
+    /// Draws nothing, only the timing of updates matters here.
+    struct NullEngine {
+        settings: ClockSettings,
+    }
+
+    impl GraphicsEngine for NullEngine {
+        fn clear(&mut self) -> Result<(), FlipClockError> {
+            Ok(())
+        }
+
+        fn present(&mut self) -> Result<(), FlipClockError> {
+            Ok(())
+        }
+
+        fn draw_rect(
+            &mut self,
+            _rect: &Rect,
+            _color: Color,
+            _border_color: Option<Color>,
+            _border_width: u32,
+            _radius: i32,
+            _filled: bool,
+        ) -> Result<(), FlipClockError> {
+            Ok(())
+        }
+
+        fn render_digit(
+            &mut self,
+            _value: u32,
+            _position: TimeDigitPosition,
+            _rect: &Rect,
+            _animation: Option<AnimationState>,
+        ) -> Result<(), FlipClockError> {
+            Ok(())
+        }
+
+        fn render_date(&mut self, _rect: &Rect, _text: &str) -> Result<(), FlipClockError> {
+            Ok(())
+        }
+
+        fn render_am_pm_indicator(
+            &mut self,
+            _hour_rect: &Rect,
+            _cards: &Rect,
+            _is_pm: bool,
+        ) -> Result<(), FlipClockError> {
+            Ok(())
+        }
+
+        fn handle_events(&mut self) -> Result<Vec<Action>, FlipClockError> {
+            Ok(Vec::new())
+        }
+
+        fn calculate_layout(&self) -> ClockLayout {
+            ClockLayout::calculate(480, 240, &self.settings)
+        }
+
+        fn get_settings(&self) -> &ClockSettings {
+            &self.settings
+        }
+
+        fn update_settings(&mut self, settings: ClockSettings) -> Result<(), FlipClockError> {
+            self.settings = settings;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn unchanged_cards_do_not_hold_up_a_split_flap_tick() {
+        let settings = ClockSettings {
+            split_flap: true,
+            show_seconds: true,
+            ..ClockSettings::default()
+        };
+        let engine = NullEngine {
+            settings: settings.clone(),
+        };
+        let start = NaiveTime::from_hms_opt(12, 34, 56).unwrap();
+        let mut clock =
+            FlipClock::new(engine, &settings).with_time_source(TimeSource::simulated(start));
+
+        clock.next_update();
+        clock.time_source.advance(Duration::from_secs(1));
+        let update = clock.next_update();
+
+        assert_eq!(
+            update.total_duration,
+            Duration::from_millis(settings.flap_interval_ms as u64)
+        );
+    }
 }
@@ -1,17 +1,23 @@
+use crate::error::FlipClockError;
+use crate::font;
+use crate::locale;
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
 use std::path::PathBuf;
 use std::str::FromStr;
 
 #[derive(Debug, thiserror::Error)]
 pub enum ConfigError {
-    #[error("Failed to read config file: {0}")]
+    #[error("Failed to read config file")]
     IoError(#[from] std::io::Error),
 
-    #[error("Failed to parse TOML: {0}")]
+    #[error("Failed to serialize TOML")]
     TomlSerError(#[from] toml::ser::Error),
 
-    #[error("Failed to deserialize TOML: {0}")]
+    #[error("Failed to parse TOML")]
     TomlDeError(#[from] toml::de::Error),
 
     #[error("Invalid hex color: {0}")]
@@ -19,6 +25,12 @@ pub enum ConfigError {
 
     #[error("Failed to find home directory")]
     HomeDirNotFound,
+
+    #[error("Unknown action: {0}")]
+    UnknownAction(String),
+
+    #[error("Invalid keybinding: {0}")]
+    InvalidKeybinding(String),
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
@@ -58,7 +70,7 @@ impl FromStr for RgbColor {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ThemeConfig {
     #[serde(default = "ThemeConfig::default_background_color")]
     pub background_color: String,
@@ -78,8 +90,22 @@ pub struct ThemeConfig {
     pub card_gap: i32,
     #[serde(default = "ThemeConfig::default_number_color")]
     pub number_color: String,
+    /// The AM/PM indicator, `number_color` when unset.
+    #[serde(default)]
+    pub indicator_color: Option<String>,
     #[serde(default = "ThemeConfig::default_font_path")]
     pub font_path: String,
+    /// Fontconfig pattern such as `"JetBrains Mono:bold"`, looked up instead of `font_path`.
+    #[serde(default)]
+    pub font_family: Option<String>,
+    #[serde(default)]
+    pub digit_font: FontConfig,
+    /// The AM/PM indicator.
+    #[serde(default)]
+    pub indicator_font: FontConfig,
+    /// Text drawn next to the cards rather than on them.
+    #[serde(default)]
+    pub label_font: FontConfig,
 }
 
 impl ThemeConfig {
@@ -127,7 +153,116 @@ impl Default for ThemeConfig {
             card_rounded_corners: Self::default_card_rounded_corners(),
             card_gap: Self::default_card_gap(),
             number_color: Self::default_number_color(),
+            indicator_color: None,
             font_path: Self::default_font_path(),
+            font_family: None,
+            digit_font: FontConfig::default(),
+            indicator_font: FontConfig::default(),
+            label_font: FontConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FontWeight {
+    Thin,
+    ExtraLight,
+    Light,
+    Regular,
+    Medium,
+    SemiBold,
+    Bold,
+    ExtraBold,
+    Black,
+}
+
+impl FontWeight {
+    /// The weight's name in a fontconfig pattern.
+    pub fn fontconfig_name(self) -> &'static str {
+        match self {
+            FontWeight::Thin => "thin",
+            FontWeight::ExtraLight => "extralight",
+            FontWeight::Light => "light",
+            FontWeight::Regular => "regular",
+            FontWeight::Medium => "medium",
+            FontWeight::SemiBold => "semibold",
+            FontWeight::Bold => "bold",
+            FontWeight::ExtraBold => "extrabold",
+            FontWeight::Black => "black",
+        }
+    }
+
+    /// The numeric weight used by CSS and SVG, 400 being regular.
+    pub fn css_value(self) -> u32 {
+        match self {
+            FontWeight::Thin => 100,
+            FontWeight::ExtraLight => 200,
+            FontWeight::Light => 300,
+            FontWeight::Regular => 400,
+            FontWeight::Medium => 500,
+            FontWeight::SemiBold => 600,
+            FontWeight::Bold => 700,
+            FontWeight::ExtraBold => 800,
+            FontWeight::Black => 900,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FontStyle {
+    #[default]
+    Normal,
+    Italic,
+    Oblique,
+}
+
+/// The font for one kind of text. Without a `path` or `family` of its own it
+/// uses the theme's `font_path` and `font_family`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FontConfig {
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub family: Option<String>,
+    /// Picks the face of that weight through fontconfig.
+    #[serde(default)]
+    pub weight: Option<FontWeight>,
+    #[serde(default)]
+    pub style: FontStyle,
+    /// Multiplies the default size of this text.
+    #[serde(default = "FontConfig::default_size_scale")]
+    pub size_scale: f32,
+    /// Extra space between characters as a fraction of the font size, negative to tighten.
+    #[serde(default)]
+    pub letter_spacing: f32,
+    /// Moves the text down by this fraction of the font size, negative to move it up.
+    #[serde(default)]
+    pub vertical_offset: f32,
+    /// Gives every digit the width of the widest one, so that proportional
+    /// figures don't shift the text as they change.
+    #[serde(default)]
+    pub tabular_figures: bool,
+}
+
+impl FontConfig {
+    fn default_size_scale() -> f32 {
+        1.0
+    }
+}
+
+impl Default for FontConfig {
+    fn default() -> Self {
+        FontConfig {
+            path: None,
+            family: None,
+            weight: None,
+            style: FontStyle::default(),
+            size_scale: Self::default_size_scale(),
+            letter_spacing: 0.0,
+            vertical_offset: 0.0,
+            tabular_figures: false,
         }
     }
 }
@@ -140,6 +275,85 @@ pub struct DisplayConfig {
     pub show_ampm: bool,
     #[serde(default)]
     pub show_leading_zero: bool,
+    /// IANA name such as `"Europe/Berlin"`, the local time zone when unset.
+    #[serde(default)]
+    pub time_zone: Option<String>,
+    /// AM and PM as written in the locale of `LC_TIME` when unset, e.g. `["vorm.", "nachm."]`.
+    #[serde(default)]
+    pub am_pm_labels: Option<[String; 2]>,
+    #[serde(default)]
+    pub numerals: Numerals,
+    #[serde(default)]
+    pub am_pm_placement: AmPmPlacement,
+    #[serde(default)]
+    pub am_pm_style: AmPmStyle,
+    /// A card with the date after the time, set in the theme's `label_font`.
+    #[serde(default)]
+    pub show_date: bool,
+    /// `strftime` format of the date card.
+    #[serde(default = "DisplayConfig::default_date_format")]
+    pub date_format: String,
+}
+
+impl DisplayConfig {
+    fn default_date_format() -> String {
+        "%a %-d %b".to_string()
+    }
+}
+
+/// Where the AM/PM indicator goes.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AmPmPlacement {
+    /// In the hour card, at the top in the morning and the bottom in the afternoon.
+    #[default]
+    Inside,
+    InsideTop,
+    InsideBottom,
+    /// Left of the cards, level with the hours.
+    Left,
+    /// Right of the cards, level with the hours.
+    Right,
+    /// Beneath the cards.
+    Below,
+    Hidden,
+}
+
+impl AmPmPlacement {
+    /// Whether the indicator takes room next to the cards.
+    pub fn is_outside(self) -> bool {
+        matches!(
+            self,
+            AmPmPlacement::Left | AmPmPlacement::Right | AmPmPlacement::Below
+        )
+    }
+}
+
+/// How the AM/PM indicator looks.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AmPmStyle {
+    /// The AM or PM label.
+    #[default]
+    Text,
+    /// A dot lit in the afternoon, like on classic clocks.
+    Dot,
+}
+
+/// How the cards write numbers.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Numerals {
+    #[default]
+    Western,
+    /// ٠١٢٣٤٥٦٧٨٩
+    ArabicIndic,
+    /// ०१२३४५६७८९
+    Devanagari,
+    /// Full width CJK digits, ０１２３４５６７８９.
+    FullWidth,
+    /// Roman numerals for the hours, Western digits for everything else.
+    Roman,
 }
 
 impl Default for DisplayConfig {
@@ -148,9 +362,106 @@ impl Default for DisplayConfig {
             show_seconds: false,
             show_ampm: false,
             show_leading_zero: false,
+            time_zone: None,
+            am_pm_labels: None,
+            numerals: Numerals::default(),
+            am_pm_placement: AmPmPlacement::default(),
+            am_pm_style: AmPmStyle::default(),
+            show_date: false,
+            date_format: Self::default_date_format(),
+        }
+    }
+}
+/// What a class of input does to a running clock.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum InputResponse {
+    Ignore,
+    Pause,
+    Exit,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InputConfig {
+    #[serde(default = "InputConfig::default_motion_threshold_px")]
+    pub motion_threshold_px: u32,
+    #[serde(default = "InputConfig::default_grace_period_ms")]
+    pub grace_period_ms: u32,
+    #[serde(default = "InputConfig::default_ignore")]
+    pub on_key: InputResponse,
+    #[serde(default = "InputConfig::default_ignore")]
+    pub on_button: InputResponse,
+    #[serde(default = "InputConfig::default_ignore")]
+    pub on_wheel: InputResponse,
+    #[serde(default = "InputConfig::default_on_motion")]
+    pub on_motion: InputResponse,
+    #[serde(default = "InputConfig::default_ignore")]
+    pub on_touch: InputResponse,
+}
+
+impl InputConfig {
+    fn default_motion_threshold_px() -> u32 {
+        20
+    }
+    fn default_grace_period_ms() -> u32 {
+        1000
+    }
+    fn default_ignore() -> InputResponse {
+        InputResponse::Ignore
+    }
+    fn default_on_motion() -> InputResponse {
+        InputResponse::Exit
+    }
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        InputConfig {
+            motion_threshold_px: Self::default_motion_threshold_px(),
+            grace_period_ms: Self::default_grace_period_ms(),
+            on_key: Self::default_ignore(),
+            on_button: Self::default_ignore(),
+            on_wheel: Self::default_ignore(),
+            on_motion: Self::default_on_motion(),
+            on_touch: Self::default_ignore(),
         }
     }
 }
+
+/// Something the user can trigger at runtime, usually through a keybinding.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Quit,
+    TogglePause,
+    ToggleFullscreen,
+    ToggleSeconds,
+    #[serde(rename = "toggle_24hour")]
+    Toggle24Hour,
+    ToggleLeadingZero,
+    NextTheme,
+    PreviousTheme,
+    StartStopTimer,
+    DimUp,
+    DimDown,
+    Screenshot,
+    /// Removes a default binding.
+    #[serde(rename = "none")]
+    Unbound,
+}
+
+impl FromStr for Action {
+    type Err = ConfigError;
+
+    /// Parses the same snake_case names used in `[keybindings]`.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        use serde::de::IntoDeserializer;
+        let deserializer: serde::de::value::StrDeserializer<serde::de::value::Error> =
+            name.trim().into_deserializer();
+        Action::deserialize(deserializer).map_err(|_| ConfigError::UnknownAction(name.to_string()))
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config {
     #[serde(default)]
@@ -160,7 +471,34 @@ pub struct Config {
     #[serde(default)]
     pub animation: AnimationConfig,
     #[serde(default)]
+    pub layout: LayoutConfig,
+    #[serde(default)]
     pub window: WindowConfig,
+    #[serde(default)]
+    pub input: InputConfig,
+    #[serde(default)]
+    pub keybindings: BTreeMap<String, Action>,
+    /// Additional themes to cycle through after `theme`.
+    #[serde(default)]
+    pub themes: Vec<ThemeConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AnimationStyle {
+    /// Classic split card: the top half falls over to reveal the new value.
+    #[default]
+    Flip,
+    /// The new value pushes the old one up and out of the card.
+    Slide,
+    /// Odometer drum that scrolls through every intermediate value.
+    Roll,
+    /// Cross-fade between the old and the new value.
+    Fade,
+    /// Split-flap board that rattles through the intermediate values.
+    Rattle,
+    /// No transition, the new value simply replaces the old one.
+    None,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -169,6 +507,14 @@ pub struct AnimationConfig {
     pub enabled: bool,
     #[serde(default = "AnimationConfig::default_duration_ms")]
     pub duration_ms: u32,
+    #[serde(default)]
+    pub style: AnimationStyle,
+    #[serde(default)]
+    pub stagger_ms: u32,
+    #[serde(default)]
+    pub split_flap: bool,
+    #[serde(default = "AnimationConfig::default_flap_interval_ms")]
+    pub flap_interval_ms: u32,
 }
 
 impl AnimationConfig {
@@ -178,6 +524,9 @@ impl AnimationConfig {
     fn default_duration_ms() -> u32 {
         500
     }
+    fn default_flap_interval_ms() -> u32 {
+        60
+    }
 }
 
 impl Default for AnimationConfig {
@@ -185,10 +534,154 @@ impl Default for AnimationConfig {
         AnimationConfig {
             enabled: Self::default_enabled(),
             duration_ms: Self::default_duration_ms(),
+            style: AnimationStyle::default(),
+            stagger_ms: 0,
+            split_flap: false,
+            flap_interval_ms: Self::default_flap_interval_ms(),
+        }
+    }
+}
+
+/// How the cards are set out in the window.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Arrangement {
+    /// In a row when the window is wider than it is tall, in a column otherwise.
+    #[default]
+    Auto,
+    Horizontal,
+    Vertical,
+    /// Hours and minutes on top, seconds and the date beneath them.
+    Grid,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LayoutConfig {
+    #[serde(default)]
+    pub arrangement: Arrangement,
+    /// Space kept free on every side, relative to the window's shorter side.
+    #[serde(default = "LayoutConfig::default_margin")]
+    pub margin: f32,
+    /// Space between cards, relative to the height of a card.
+    #[serde(default = "LayoutConfig::default_spacing")]
+    pub spacing: f32,
+    /// Width of a card divided by its height.
+    #[serde(default = "LayoutConfig::default_card_aspect_ratio")]
+    pub card_aspect_ratio: f32,
+    /// Size of the seconds card relative to the hours and minutes, up to 1.0.
+    #[serde(default = "LayoutConfig::default_seconds_scale")]
+    pub seconds_scale: f32,
+    /// Where the cards sit in the room the margins leave.
+    #[serde(default)]
+    pub alignment: Anchor,
+}
+
+impl LayoutConfig {
+    fn default_margin() -> f32 {
+        0.175
+    }
+    fn default_spacing() -> f32 {
+        0.09
+    }
+    fn default_card_aspect_ratio() -> f32 {
+        1.0
+    }
+    fn default_seconds_scale() -> f32 {
+        1.0
+    }
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        LayoutConfig {
+            arrangement: Arrangement::default(),
+            margin: Self::default_margin(),
+            spacing: Self::default_spacing(),
+            card_aspect_ratio: Self::default_card_aspect_ratio(),
+            seconds_scale: Self::default_seconds_scale(),
+            alignment: Anchor::default(),
+        }
+    }
+}
+
+/// A display picked by its index or by (part of) its name, e.g. `1` or `"HDMI-1"`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum DisplaySelector {
+    Index(u32),
+    Name(String),
+}
+
+impl DisplaySelector {
+    pub fn matches(&self, index: u32, name: &str) -> bool {
+        match self {
+            DisplaySelector::Index(selected) => *selected == index,
+            DisplaySelector::Name(selected) => {
+                name.to_lowercase().contains(&selected.to_lowercase())
+            }
+        }
+    }
+}
+
+impl fmt::Display for DisplaySelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisplaySelector::Index(index) => write!(f, "{}", index),
+            DisplaySelector::Name(name) => write!(f, "\"{}\"", name),
         }
     }
 }
 
+/// How the clock is spread over the connected displays.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MultiDisplay {
+    /// One clock on the selected display.
+    #[default]
+    Single,
+    /// A separate clock on every display.
+    Each,
+    /// One clock stretched across the bounding box of all displays.
+    Span,
+}
+
+/// Point of the display a positioned window is attached to.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Anchor {
+    #[default]
+    Center,
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+/// Where the window stays relative to other windows.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Stacking {
+    #[default]
+    Normal,
+    Above,
+    Below,
+}
+
+/// Overrides for the clock on one display when `multi_display = "each"`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScreenConfig {
+    pub display: DisplaySelector,
+    /// Index into the theme list, `0` being `[theme]` and `1` the first of `[[themes]]`.
+    #[serde(default)]
+    pub theme: Option<usize>,
+    #[serde(default)]
+    pub time_zone: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct WindowConfig {
     #[serde(default = "WindowConfig::default_width")]
@@ -199,6 +692,33 @@ pub struct WindowConfig {
     pub fullscreen: bool,
     #[serde(default = "WindowConfig::default_any_close")]
     pub close_on_any_input: bool,
+    /// Lets the desktop show through the background, as much as `background_opacity` allows.
+    #[serde(default)]
+    pub transparent: bool,
+    /// A small clock that lives on the desktop: never fullscreen and never closed by input.
+    #[serde(default)]
+    pub widget: bool,
+    #[serde(default)]
+    pub anchor: Anchor,
+    /// Distance from the anchored edges, towards the middle of the display.
+    #[serde(default)]
+    pub offset_x: i32,
+    #[serde(default)]
+    pub offset_y: i32,
+    #[serde(default)]
+    pub stacking: Stacking,
+    #[serde(default)]
+    pub skip_taskbar: bool,
+    /// Lets clicks pass through to the windows below.
+    #[serde(default)]
+    pub click_through: bool,
+    /// Display to open on, the primary one when unset.
+    #[serde(default)]
+    pub display: Option<DisplaySelector>,
+    #[serde(default)]
+    pub multi_display: MultiDisplay,
+    #[serde(default)]
+    pub screens: Vec<ScreenConfig>,
 }
 
 impl WindowConfig {
@@ -223,6 +743,17 @@ impl Default for WindowConfig {
             height: Self::default_height(),
             fullscreen: true,
             close_on_any_input: false,
+            transparent: false,
+            widget: false,
+            anchor: Anchor::default(),
+            offset_x: 0,
+            offset_y: 0,
+            stacking: Stacking::default(),
+            skip_taskbar: false,
+            click_through: false,
+            display: None,
+            multi_display: MultiDisplay::default(),
+            screens: Vec::new(),
         }
     }
 }
@@ -233,7 +764,11 @@ impl Default for Config {
             theme: ThemeConfig::default(),
             display: DisplayConfig::default(),
             animation: AnimationConfig::default(),
+            layout: LayoutConfig::default(),
             window: WindowConfig::default(),
+            input: InputConfig::default(),
+            keybindings: BTreeMap::new(),
+            themes: Vec::new(),
         }
     }
 }
@@ -275,26 +810,111 @@ impl Config {
         Ok(path)
     }
 
+    fn default_keybindings() -> Vec<(String, Action)> {
+        [
+            ("Escape", Action::Quit),
+            ("Return", Action::Quit),
+            ("Space", Action::Quit),
+            ("F11", Action::ToggleFullscreen),
+            ("S", Action::ToggleSeconds),
+            ("H", Action::Toggle24Hour),
+            ("Z", Action::ToggleLeadingZero),
+            ("Right", Action::NextTheme),
+            ("Left", Action::PreviousTheme),
+            ("T", Action::StartStopTimer),
+            ("Down", Action::DimUp),
+            ("Up", Action::DimDown),
+            ("P", Action::Screenshot),
+        ]
+        .into_iter()
+        .map(|(key, action)| (key.to_string(), action))
+        .collect()
+    }
+
     pub fn to_clock_settings(&self) -> ClockSettings {
+        // `close_on_any_input` predates the per-class settings and still upgrades
+        // them to exit. A widget stays put whatever the input.
+        let any_input = |response: InputResponse| {
+            if self.window.widget {
+                InputResponse::Ignore
+            } else if self.window.close_on_any_input {
+                InputResponse::Exit
+            } else {
+                response
+            }
+        };
+
         ClockSettings {
             background_color: self.theme.background_color.parse().unwrap_or_default(),
             background_opacity: self.theme.background_opacity,
             font_color: self.theme.number_color.parse().unwrap_or_default(),
+            indicator_color: parse_color(self.theme.indicator_color.as_deref()),
             show_seconds: self.display.show_seconds,
             show_leading_zero: self.display.show_leading_zero,
             use_24hour: !self.display.show_ampm,
             width: self.window.width,
             height: self.window.height,
-            fullscreen: self.window.fullscreen,
-            close_on_any_input: self.window.close_on_any_input,
+            fullscreen: self.window.fullscreen && !self.window.widget,
+            transparent: self.window.transparent,
+            anchor: self.window.anchor,
+            offset_x: self.window.offset_x,
+            offset_y: self.window.offset_y,
+            stacking: self.window.stacking,
+            skip_taskbar: self.window.skip_taskbar,
+            click_through: self.window.click_through,
+            screensaver_window: None,
+            display: self.window.display.clone(),
+            multi_display: self.window.multi_display,
+            screens: self.window.screens.clone(),
+            time_zone: parse_time_zone(self.display.time_zone.as_deref()),
+            am_pm_labels: self
+                .display
+                .am_pm_labels
+                .clone()
+                .unwrap_or_else(|| locale::meridiem(locale::time_locale().as_deref())),
+            numerals: self.display.numerals,
+            am_pm_placement: self.display.am_pm_placement,
+            am_pm_style: self.display.am_pm_style,
+            show_date: self.display.show_date,
+            date_format: self.display.date_format.clone(),
+            layout: self.layout.clone(),
+            motion_threshold_px: self.input.motion_threshold_px,
+            input_grace_period_ms: self.input.grace_period_ms,
+            on_key: any_input(self.input.on_key),
+            on_button: any_input(self.input.on_button),
+            on_wheel: any_input(self.input.on_wheel),
+            on_motion: if self.window.widget {
+                InputResponse::Ignore
+            } else {
+                self.input.on_motion
+            },
+            on_touch: any_input(self.input.on_touch),
             animate_flip: self.animation.enabled,
             animation_duration_ms: self.animation.duration_ms,
+            animation_style: self.animation.style,
+            animation_stagger_ms: self.animation.stagger_ms,
+            split_flap: self.animation.split_flap,
+            flap_interval_ms: self.animation.flap_interval_ms,
             font_path: self.theme.font_path.clone(),
+            font_family: self.theme.font_family.clone(),
+            digit_font: self.theme.digit_font.clone(),
+            indicator_font: self.theme.indicator_font.clone(),
+            label_font: self.theme.label_font.clone(),
             card_color: self.theme.card_color.parse().unwrap_or_default(),
             card_border_color: self.theme.card_border_color.parse().unwrap_or_default(),
             card_border_size: self.theme.card_border_size,
             card_gap: self.theme.card_gap,
             card_rounded_corners: self.theme.card_rounded_corners,
+            // User bindings come last so they override the defaults for the same keys.
+            keybindings: Self::default_keybindings()
+                .into_iter()
+                .chain(self.keybindings.clone())
+                .collect(),
+            themes: std::iter::once(self.theme.clone())
+                .chain(self.themes.iter().cloned())
+                .collect(),
+            theme_index: 0,
+            dim_level: 0.0,
         }
     }
 }
@@ -308,17 +928,137 @@ pub struct ClockSettings {
     pub show_leading_zero: bool,
     pub background_color: RgbColor,
     pub background_opacity: f32,
+    pub transparent: bool,
+    pub anchor: Anchor,
+    pub offset_x: i32,
+    pub offset_y: i32,
+    pub stacking: Stacking,
+    pub skip_taskbar: bool,
+    pub click_through: bool,
     pub font_color: RgbColor,
+    /// The AM/PM indicator's color, `font_color` when unset.
+    pub indicator_color: Option<RgbColor>,
     pub animate_flip: bool,
     pub animation_duration_ms: u32,
-    pub close_on_any_input: bool,
+    pub animation_style: AnimationStyle,
+    pub animation_stagger_ms: u32,
+    pub split_flap: bool,
+    pub flap_interval_ms: u32,
+    /// Foreign window handed to us by a screensaver host, drawn into instead of our own.
+    pub screensaver_window: Option<u64>,
+    pub display: Option<DisplaySelector>,
+    pub multi_display: MultiDisplay,
+    pub screens: Vec<ScreenConfig>,
+    pub time_zone: Option<Tz>,
+    /// What the AM/PM indicator reads, morning first.
+    pub am_pm_labels: [String; 2],
+    pub numerals: Numerals,
+    pub am_pm_placement: AmPmPlacement,
+    pub am_pm_style: AmPmStyle,
+    pub show_date: bool,
+    pub date_format: String,
+    pub layout: LayoutConfig,
+    pub motion_threshold_px: u32,
+    pub input_grace_period_ms: u32,
+    pub on_key: InputResponse,
+    pub on_button: InputResponse,
+    pub on_wheel: InputResponse,
+    pub on_motion: InputResponse,
+    pub on_touch: InputResponse,
     pub show_seconds: bool,
     pub font_path: String,
+    pub font_family: Option<String>,
+    pub digit_font: FontConfig,
+    pub indicator_font: FontConfig,
+    pub label_font: FontConfig,
     pub card_color: RgbColor,
     pub card_border_color: RgbColor,
     pub card_border_size: u32,
     pub card_gap: i32,
     pub card_rounded_corners: bool,
+    pub keybindings: Vec<(String, Action)>,
+    pub themes: Vec<ThemeConfig>,
+    pub theme_index: usize,
+    /// How much the display is darkened, from 0.0 (not at all) to 1.0 (black).
+    pub dim_level: f32,
+}
+
+impl ClockSettings {
+    /// The file to draw `font` with, looked up as described at `font::find_font`.
+    pub fn font_file(&self, font: &FontConfig) -> Result<String, FlipClockError> {
+        let (path, family) = match (&font.path, &font.family) {
+            (None, None) => (&self.font_path, self.font_family.as_deref()),
+            (path, family) => (path.as_ref().unwrap_or(&self.font_path), family.as_deref()),
+        };
+        font::find_font(path, family, font.weight, font.style)
+    }
+
+    /// Whether `other` draws with different fonts.
+    pub fn font_changed(&self, other: &ClockSettings) -> bool {
+        self.font_path != other.font_path
+            || self.font_family != other.font_family
+            || self.digit_font != other.digit_font
+            || self.indicator_font != other.indicator_font
+            || self.label_font != other.label_font
+            // Characters the fonts may lack, see `FontRole::file`.
+            || self.numerals != other.numerals
+            || self.am_pm_labels != other.am_pm_labels
+    }
+
+    /// Switches to one of `themes`, wrapping around at either end.
+    pub fn select_theme(&mut self, index: isize) {
+        if self.themes.is_empty() {
+            return;
+        }
+        self.theme_index = index.rem_euclid(self.themes.len() as isize) as usize;
+
+        let theme = &self.themes[self.theme_index];
+        self.background_color = theme.background_color.parse().unwrap_or_default();
+        self.background_opacity = theme.background_opacity;
+        self.font_color = theme.number_color.parse().unwrap_or_default();
+        self.indicator_color = parse_color(theme.indicator_color.as_deref());
+        self.font_path = theme.font_path.clone();
+        self.font_family = theme.font_family.clone();
+        self.digit_font = theme.digit_font.clone();
+        self.indicator_font = theme.indicator_font.clone();
+        self.label_font = theme.label_font.clone();
+        self.card_color = theme.card_color.parse().unwrap_or_default();
+        self.card_border_color = theme.card_border_color.parse().unwrap_or_default();
+        self.card_border_size = theme.card_border_size;
+        self.card_gap = theme.card_gap;
+        self.card_rounded_corners = theme.card_rounded_corners;
+    }
+
+    /// Settings for the clock on one display, with that display's `[[window.screens]]`
+    /// overrides applied.
+    pub fn for_display(&self, index: u32, name: &str) -> ClockSettings {
+        let mut settings = self.clone();
+        settings.display = Some(DisplaySelector::Index(index));
+
+        if let Some(screen) = self
+            .screens
+            .iter()
+            .find(|screen| screen.display.matches(index, name))
+        {
+            if let Some(theme) = screen.theme {
+                settings.select_theme(theme as isize);
+            }
+            if let Some(time_zone) = screen.time_zone.as_deref() {
+                settings.time_zone = parse_time_zone(Some(time_zone));
+            }
+        }
+        settings
+    }
+}
+
+/// Unknown zone names fall back to local time, like invalid colors fall back to white.
+fn parse_time_zone(name: Option<&str>) -> Option<Tz> {
+    name.and_then(|name| name.parse().ok())
+}
+
+/// An optional color, white when set but invalid like the required ones.
+fn parse_color(hex: Option<&str>) -> Option<RgbColor> {
+    hex.map(|hex| hex.parse().unwrap_or_default())
 }
 
 impl Default for ClockSettings {
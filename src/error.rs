@@ -0,0 +1,62 @@
+use crate::config::ConfigError;
+use std::io;
+
+/// Everything that can go wrong, grouped by what the user has to fix.
+/// Each category exits with its own status, see `exit_code`.
+#[derive(Debug, thiserror::Error)]
+pub enum FlipClockError {
+    #[error("{0}")]
+    Usage(String),
+
+    #[error("Invalid configuration")]
+    Config(#[from] ConfigError),
+
+    #[error("Failed to load font {path}")]
+    Font {
+        path: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// Opening a window, the terminal or the framebuffer.
+    #[error("Failed to set up the display: {0}")]
+    Video(String),
+
+    #[error("Failed to draw: {0}")]
+    Render(String),
+
+    #[error("{context}")]
+    Io {
+        context: String,
+        #[source]
+        source: io::Error,
+    },
+}
+
+impl FlipClockError {
+    pub fn font(path: &str, source: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        FlipClockError::Font {
+            path: path.to_string(),
+            source: source.into(),
+        }
+    }
+
+    pub fn io(context: impl Into<String>, source: io::Error) -> Self {
+        FlipClockError::Io {
+            context: context.into(),
+            source,
+        }
+    }
+
+    /// Process exit status for this kind of error, so that scripts can tell them apart.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            FlipClockError::Usage(_) => 2,
+            FlipClockError::Config(_) => 3,
+            FlipClockError::Font { .. } => 4,
+            FlipClockError::Video(_) => 5,
+            FlipClockError::Render(_) => 6,
+            FlipClockError::Io { .. } => 7,
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use crate::error::FlipClockError;
+use crate::graphics_engine::Frame;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+pub fn write_png(path: &Path, frame: &Frame) -> Result<(), FlipClockError> {
+    write_atomically(path, |file| {
+        let mut encoder = png::Encoder::new(file, frame.width, frame.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&frame.pixels)?;
+        writer.finish()?;
+        Ok(())
+    })
+}
+
+/// Writes to a temporary file next to `path` and renames it into place, so
+/// that e.g. a wallpaper setter never picks up a half written image.
+pub fn write_atomically(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+) -> Result<(), FlipClockError> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| FlipClockError::Usage(format!("Not a file: {}", path.display())))?;
+    let temporary_path = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+
+    let result = File::create(&temporary_path)
+        .and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            write(&mut writer)?;
+            writer.flush()
+        })
+        .and_then(|_| std::fs::rename(&temporary_path, path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temporary_path);
+    }
+    result.map_err(|e| FlipClockError::io(format!("Failed to write {}", path.display()), e))
+}
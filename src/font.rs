@@ -0,0 +1,113 @@
+use crate::config::{FontStyle, FontWeight};
+use crate::error::FlipClockError;
+use crate::raster::GlyphFont;
+use std::path::Path;
+use std::process::Command;
+
+/// Tried in order when neither the configured family nor path can be found:
+/// DejaVu Sans where the common distributions install it, then the stock
+/// sans serif fonts of macOS and Windows.
+const FALLBACK_PATHS: &[&str] = &[
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",               // Arch
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",   // Debian, Ubuntu
+    "/usr/share/fonts/dejavu-sans-fonts/DejaVuSans.ttf", // Fedora
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",            // openSUSE, Alpine
+    "/usr/local/share/fonts/dejavu/DejaVuSans.ttf",      // FreeBSD
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+];
+
+/// The font file to draw with: the best match for `family` if one is set and
+/// fontconfig is installed, `path` if it exists, the first fallback font that
+/// exists, or whatever sans serif font fontconfig picks. A `weight` or `style`
+/// looks up that face of `family`, or of the family the file belongs to.
+pub fn find_font(
+    path: &str,
+    family: Option<&str>,
+    weight: Option<FontWeight>,
+    style: FontStyle,
+) -> Result<String, FlipClockError> {
+    let face = |family: &str| {
+        let mut pattern = family.to_string();
+        if let Some(weight) = weight {
+            pattern.push_str(&format!(":weight={}", weight.fontconfig_name()));
+        }
+        match style {
+            FontStyle::Normal => {}
+            FontStyle::Italic => pattern.push_str(":slant=italic"),
+            FontStyle::Oblique => pattern.push_str(":slant=oblique"),
+        }
+        match_family(&pattern)
+    };
+
+    if let Some(file) = family.and_then(face) {
+        return Ok(file);
+    }
+    let file = Path::new(path)
+        .is_file()
+        .then(|| path.to_string())
+        .or_else(|| {
+            FALLBACK_PATHS
+                .iter()
+                .find(|fallback| Path::new(fallback).is_file())
+                .map(|fallback| fallback.to_string())
+        })
+        .or_else(|| match_family("sans-serif"))
+        .ok_or_else(|| {
+            FlipClockError::font(
+                family.unwrap_or(path),
+                "not found, and no fallback font is installed either. \
+                 Set font_path to a TrueType or OpenType file",
+            )
+        })?;
+
+    let styled = weight.is_some() || style != FontStyle::Normal;
+    Ok(styled
+        .then(|| family_name(&file))
+        .flatten()
+        .and_then(|family| face(&family))
+        .unwrap_or(file))
+}
+
+/// `file` if it has glyphs for every character of `text`, otherwise the font
+/// of the same family fontconfig suggests for the missing ones, if it has them all.
+pub fn covering(file: String, text: &str) -> String {
+    let Ok(font) = GlyphFont::load(&file) else {
+        return file;
+    };
+    let missing: Vec<char> = text
+        .chars()
+        .filter(|c| !c.is_whitespace() && !font.has_glyph(*c))
+        .collect();
+    if missing.is_empty() {
+        return file;
+    }
+
+    let charset: Vec<String> = missing.iter().map(|c| format!("{:x}", *c as u32)).collect();
+    let pattern = format!(
+        "{}:charset={}",
+        font.family_name().unwrap_or_default(),
+        charset.join(" ")
+    );
+    match_family(&pattern)
+        .filter(|candidate| {
+            GlyphFont::load(candidate).is_ok_and(|font| missing.iter().all(|c| font.has_glyph(*c)))
+        })
+        .unwrap_or(file)
+}
+
+/// Asks fontconfig for the file of the closest outline font to `pattern`, like `fc-match`.
+fn match_family(pattern: &str) -> Option<String> {
+    let output = Command::new("fc-match")
+        .arg("--format=%{file}")
+        .arg(format!("{}:scalable=true", pattern))
+        .output()
+        .ok()?;
+    let file = String::from_utf8(output.stdout).ok()?;
+    (output.status.success() && Path::new(&file).is_file()).then_some(file)
+}
+
+/// The family the font file at `path` belongs to, if it can be read.
+fn family_name(path: &str) -> Option<String> {
+    GlyphFont::load(path).ok()?.family_name()
+}
@@ -0,0 +1,573 @@
+use crate::error::FlipClockError;
+use crate::graphics_engine::Color;
+use crate::input::InputEvent;
+use crate::keybindings::{KeyCombo, Modifiers};
+use crate::raster::Canvas;
+use crate::software_engine::{Surface, SurfaceEvent};
+use evdev::{Device, EventSummary, KeyCode, RelativeAxisCode};
+use std::fs::{File, OpenOptions};
+use std::io::ErrorKind;
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::FileExt;
+
+/// `FBIOGET_VSCREENINFO` and `FBIOGET_FSCREENINFO` from `linux/fb.h`.
+const FBIOGET_VSCREENINFO: libc::c_ulong = 0x4600;
+const FBIOGET_FSCREENINFO: libc::c_ulong = 0x4602;
+
+/// `KDSETMODE` and its modes from `linux/kd.h`.
+const KDSETMODE: libc::c_ulong = 0x4B3A;
+const KD_TEXT: libc::c_ulong = 0;
+const KD_GRAPHICS: libc::c_ulong = 1;
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+#[allow(dead_code)]
+struct FbBitfield {
+    offset: u32,
+    length: u32,
+    msb_right: u32,
+}
+
+/// `struct fb_var_screeninfo`.
+#[repr(C)]
+#[allow(dead_code)] // Mirrors the kernel's layout.
+#[derive(Default)]
+struct FbVarScreenInfo {
+    xres: u32,
+    yres: u32,
+    xres_virtual: u32,
+    yres_virtual: u32,
+    xoffset: u32,
+    yoffset: u32,
+    bits_per_pixel: u32,
+    grayscale: u32,
+    red: FbBitfield,
+    green: FbBitfield,
+    blue: FbBitfield,
+    transp: FbBitfield,
+    nonstd: u32,
+    activate: u32,
+    height: u32,
+    width: u32,
+    accel_flags: u32,
+    timings: [u32; 11],
+    reserved: [u32; 4],
+}
+
+/// `struct fb_fix_screeninfo`.
+#[repr(C)]
+#[allow(dead_code)]
+#[derive(Default)]
+struct FbFixScreenInfo {
+    id: [libc::c_char; 16],
+    smem_start: libc::c_ulong,
+    smem_len: u32,
+    kind: u32,
+    type_aux: u32,
+    visual: u32,
+    xpanstep: u16,
+    ypanstep: u16,
+    ywrapstep: u16,
+    line_length: u32,
+    mmio_start: libc::c_ulong,
+    mmio_len: u32,
+    accel: u32,
+    capabilities: u16,
+    reserved: [u16; 2],
+}
+
+/// Bits of a color channel within a pixel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Channel {
+    pub offset: u32,
+    pub length: u32,
+}
+
+/// How the visible pixels are laid out in a framebuffer device.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FramebufferInfo {
+    pub width: u32,
+    pub height: u32,
+    /// Bytes from the start of one row to the next, padding included.
+    pub line_length: u32,
+    pub bits_per_pixel: u32,
+    pub red: Channel,
+    pub green: Channel,
+    pub blue: Channel,
+    /// Where the visible area starts, for panned or double buffered devices.
+    pub offset: u64,
+}
+
+impl FramebufferInfo {
+    /// Asks the driver behind `file` for its current mode.
+    fn query(file: &File) -> Result<Self, FlipClockError> {
+        let mut var = FbVarScreenInfo::default();
+        let mut fix = FbFixScreenInfo::default();
+        unsafe {
+            if libc::ioctl(file.as_raw_fd(), FBIOGET_VSCREENINFO, &mut var) < 0
+                || libc::ioctl(file.as_raw_fd(), FBIOGET_FSCREENINFO, &mut fix) < 0
+            {
+                return Err(FlipClockError::io(
+                    "Not a framebuffer device",
+                    std::io::Error::last_os_error(),
+                ));
+            }
+        }
+        if !matches!(var.bits_per_pixel, 16 | 24 | 32) {
+            return Err(FlipClockError::Video(format!(
+                "Unsupported framebuffer depth: {} bits per pixel",
+                var.bits_per_pixel
+            )));
+        }
+
+        let channel = |field: FbBitfield| Channel {
+            offset: field.offset,
+            length: field.length,
+        };
+        Ok(FramebufferInfo {
+            width: var.xres,
+            height: var.yres,
+            line_length: fix.line_length,
+            bits_per_pixel: var.bits_per_pixel,
+            red: channel(var.red),
+            green: channel(var.green),
+            blue: channel(var.blue),
+            offset: var.yoffset as u64 * fix.line_length as u64
+                + var.xoffset as u64 * var.bits_per_pixel as u64 / 8,
+        })
+    }
+
+    fn bytes_per_pixel(&self) -> usize {
+        self.bits_per_pixel as usize / 8
+    }
+
+    /// `color` as the bytes of one pixel, blended over black.
+    fn encode(&self, color: Color, pixel: &mut [u8]) {
+        let value = [
+            (self.red, color.r),
+            (self.green, color.g),
+            (self.blue, color.b),
+        ]
+        .into_iter()
+        .fold(0u32, |value, (channel, level)| {
+            let level = level as u32 * color.a as u32 / 255;
+            value | (level >> (8 - channel.length.min(8))) << channel.offset
+        });
+        pixel.copy_from_slice(&value.to_le_bytes()[..pixel.len()]);
+    }
+}
+
+/// A Linux framebuffer device such as `/dev/fb0`, with keyboards and mice
+/// read straight from evdev since there is no display server to ask.
+pub struct Framebuffer {
+    file: File,
+    info: FramebufferInfo,
+    /// Last frame written, so rows that did not change are skipped.
+    frame: Vec<u8>,
+    devices: Vec<Device>,
+    modifiers: Modifiers,
+    pointer: (i32, i32),
+    /// The virtual terminal switched to graphics mode, so the console does not draw over the clock.
+    console: Option<File>,
+}
+
+impl Framebuffer {
+    /// Opens the framebuffer at `path` and grabs every keyboard and mouse.
+    pub fn open(path: &str) -> Result<Self, FlipClockError> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .map_err(|e| FlipClockError::io(format!("Failed to open {}", path), e))?;
+        let info = FramebufferInfo::query(&file)?;
+
+        let mut framebuffer = Framebuffer::with_file(file, info);
+        framebuffer.devices = open_input_devices();
+        framebuffer.console = graphics_console();
+        Ok(framebuffer)
+    }
+
+    /// A framebuffer laid out as `info` in an already open file, without input devices.
+    pub fn with_file(file: File, info: FramebufferInfo) -> Self {
+        Framebuffer {
+            file,
+            info,
+            frame: Vec::new(),
+            devices: Vec::new(),
+            modifiers: Modifiers::default(),
+            pointer: (info.width as i32 / 2, info.height as i32 / 2),
+            console: None,
+        }
+    }
+
+    fn surface_event(&mut self, event: EventSummary) -> Option<SurfaceEvent> {
+        match event {
+            EventSummary::Key(_, code, value) => self.key_event(code, value),
+            EventSummary::RelativeAxis(_, RelativeAxisCode::REL_WHEEL, _)
+            | EventSummary::RelativeAxis(_, RelativeAxisCode::REL_HWHEEL, _) => {
+                Some(SurfaceEvent::Input(InputEvent::Wheel))
+            }
+            EventSummary::RelativeAxis(_, axis, value) => {
+                let (x, y) = &mut self.pointer;
+                match axis {
+                    RelativeAxisCode::REL_X => *x = (*x + value).clamp(0, self.info.width as i32),
+                    RelativeAxisCode::REL_Y => *y = (*y + value).clamp(0, self.info.height as i32),
+                    _ => return None,
+                }
+                Some(SurfaceEvent::Input(InputEvent::Motion { x: *x, y: *y }))
+            }
+            _ => None,
+        }
+    }
+
+    /// `value` is 1 for a press, 2 for autorepeat and 0 for a release.
+    fn key_event(&mut self, code: KeyCode, value: i32) -> Option<SurfaceEvent> {
+        let pressed = value != 0;
+        match code {
+            KeyCode::KEY_LEFTCTRL | KeyCode::KEY_RIGHTCTRL => self.modifiers.ctrl = pressed,
+            KeyCode::KEY_LEFTALT | KeyCode::KEY_RIGHTALT => self.modifiers.alt = pressed,
+            KeyCode::KEY_LEFTSHIFT | KeyCode::KEY_RIGHTSHIFT => self.modifiers.shift = pressed,
+            KeyCode::KEY_LEFTMETA | KeyCode::KEY_RIGHTMETA => self.modifiers.logo = pressed,
+            _ if value != 1 => {}
+            KeyCode::BTN_LEFT | KeyCode::BTN_RIGHT | KeyCode::BTN_MIDDLE => {
+                return Some(SurfaceEvent::Input(InputEvent::Button));
+            }
+            KeyCode::BTN_TOUCH => return Some(SurfaceEvent::Input(InputEvent::Touch)),
+            // Grabbed keyboards no longer reach the console, so this is the only way to interrupt.
+            KeyCode::KEY_C if self.modifiers.ctrl => return Some(SurfaceEvent::Quit),
+            _ => {
+                return Some(match key_name(code) {
+                    Some(name) => SurfaceEvent::Key(KeyCombo::new(&name, self.modifiers)),
+                    None => SurfaceEvent::Input(InputEvent::Key),
+                });
+            }
+        }
+        None
+    }
+}
+
+impl Surface for Framebuffer {
+    fn size(&self) -> Result<(u32, u32), FlipClockError> {
+        Ok((self.info.width, self.info.height))
+    }
+
+    fn present(&mut self, canvas: &Canvas) -> Result<(), FlipClockError> {
+        let width = canvas.width().min(self.info.width);
+        let height = canvas.height().min(self.info.height);
+        let bytes_per_pixel = self.info.bytes_per_pixel();
+        let row_length = width as usize * bytes_per_pixel;
+        // Nothing written at this size yet, every row has to go out.
+        let redraw = self.frame.len() != row_length * height as usize;
+        if redraw {
+            self.frame = vec![0; row_length * height as usize];
+        }
+
+        let mut row = vec![0; row_length];
+        for y in 0..height {
+            for (x, pixel) in row.chunks_exact_mut(bytes_per_pixel).enumerate() {
+                self.info.encode(canvas.pixel(x as u32, y), pixel);
+            }
+            let previous = &mut self.frame[y as usize * row_length..][..row_length];
+            if !redraw && previous == row.as_slice() {
+                continue;
+            }
+            previous.copy_from_slice(&row);
+
+            let position = self.info.offset + y as u64 * self.info.line_length as u64;
+            self.file
+                .write_all_at(&row, position)
+                .map_err(|e| FlipClockError::io("Failed to write to the framebuffer", e))?;
+        }
+        Ok(())
+    }
+
+    fn poll_events(&mut self) -> Result<Vec<SurfaceEvent>, FlipClockError> {
+        let mut raw_events = Vec::new();
+        // Devices that fail for anything but having no events were unplugged.
+        self.devices
+            .retain_mut(|device| match device.fetch_events() {
+                Ok(events) => {
+                    raw_events.extend(events.map(|event| event.destructure()));
+                    true
+                }
+                Err(e) => e.kind() == ErrorKind::WouldBlock,
+            });
+
+        Ok(raw_events
+            .into_iter()
+            .filter_map(|event| self.surface_event(event))
+            .collect())
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        if let Some(console) = &self.console {
+            unsafe { libc::ioctl(console.as_raw_fd(), KDSETMODE, KD_TEXT) };
+        }
+    }
+}
+
+/// Every device under `/dev/input` with keys or a wheel, grabbed and non-blocking.
+fn open_input_devices() -> Vec<Device> {
+    evdev::enumerate()
+        .map(|(_, device)| device)
+        .filter(|device| {
+            device.supported_keys().is_some() || device.supported_relative_axes().is_some()
+        })
+        .filter_map(|mut device| {
+            device.set_nonblocking(true).ok()?;
+            // Keys would otherwise also end up in the shell on the console.
+            let _ = device.grab();
+            Some(device)
+        })
+        .collect()
+}
+
+/// Switches the controlling terminal to graphics mode, if it is a virtual console.
+fn graphics_console() -> Option<File> {
+    let console = File::options().write(true).open("/dev/tty").ok()?;
+    let result = unsafe { libc::ioctl(console.as_raw_fd(), KDSETMODE, KD_GRAPHICS) };
+    (result == 0).then_some(console)
+}
+
+/// SDL's name for a key, so `[keybindings]` work the same as in a window.
+fn key_name(code: KeyCode) -> Option<String> {
+    // `KeyCode`'s debug output is the kernel's name, `KEY_A`, `KEY_F11`, ...
+    let debug = format!("{:?}", code);
+    let name = debug.strip_prefix("KEY_")?;
+    let name = match name {
+        "ESC" => "Escape",
+        "ENTER" | "KPENTER" => "Return",
+        "SPACE" => "Space",
+        "BACKSPACE" => "Backspace",
+        "TAB" => "Tab",
+        "LEFT" => "Left",
+        "RIGHT" => "Right",
+        "UP" => "Up",
+        "DOWN" => "Down",
+        "DELETE" => "Delete",
+        "INSERT" => "Insert",
+        "HOME" => "Home",
+        "END" => "End",
+        "PAGEUP" => "PageUp",
+        "PAGEDOWN" => "PageDown",
+        _ if name.len() == 1 => name,
+        _ if name
+            .strip_prefix('F')
+            .is_some_and(|n| n.parse::<u8>().is_ok()) =>
+        {
+            name
+        }
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn xrgb8888(width: u32, height: u32, line_length: u32) -> FramebufferInfo {
+        FramebufferInfo {
+            width,
+            height,
+            line_length,
+            bits_per_pixel: 32,
+            red: Channel {
+                offset: 16,
+                length: 8,
+            },
+            green: Channel {
+                offset: 8,
+                length: 8,
+            },
+            blue: Channel {
+                offset: 0,
+                length: 8,
+            },
+            offset: 0,
+        }
+    }
+
+    fn rgb565(width: u32, height: u32) -> FramebufferInfo {
+        FramebufferInfo {
+            width,
+            height,
+            line_length: width * 2,
+            bits_per_pixel: 16,
+            red: Channel {
+                offset: 11,
+                length: 5,
+            },
+            green: Channel {
+                offset: 5,
+                length: 6,
+            },
+            blue: Channel {
+                offset: 0,
+                length: 5,
+            },
+            offset: 0,
+        }
+    }
+
+    /// A framebuffer backed by a temporary file filled with `fill`, like a device would be.
+    fn fake(info: FramebufferInfo, fill: u8) -> (Framebuffer, File) {
+        let file = tempfile::tempfile().unwrap();
+        let length = info.offset + info.line_length as u64 * info.height as u64;
+        file.write_all_at(&vec![fill; length as usize], 0).unwrap();
+        (
+            Framebuffer::with_file(file.try_clone().unwrap(), info),
+            file,
+        )
+    }
+
+    fn contents(mut file: &File) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn screen_info_matches_the_kernel() {
+        assert_eq!(std::mem::size_of::<FbVarScreenInfo>(), 160);
+    }
+
+    #[test]
+    fn size_is_the_visible_resolution() {
+        let (framebuffer, _) = fake(xrgb8888(320, 240, 1280), 0);
+        assert_eq!(framebuffer.size().unwrap(), (320, 240));
+    }
+
+    #[test]
+    fn writes_xrgb8888_pixels() {
+        let (mut framebuffer, file) = fake(xrgb8888(2, 1, 8), 0);
+        let mut canvas = Canvas::new(2, 1);
+        canvas.fill(Color::rgb(0x12, 0x34, 0x56));
+        canvas.fill_rect(
+            &crate::graphics_engine::Rect::new(1, 0, 1, 1),
+            Color::rgb(255, 0, 0),
+        );
+        framebuffer.present(&canvas).unwrap();
+
+        assert_eq!(
+            contents(&file),
+            [0x56, 0x34, 0x12, 0x00, 0x00, 0x00, 0xff, 0x00]
+        );
+    }
+
+    #[test]
+    fn writes_rgb565_pixels() {
+        let (mut framebuffer, file) = fake(rgb565(2, 1), 0);
+        let mut canvas = Canvas::new(2, 1);
+        canvas.fill(Color::rgb(255, 255, 255));
+        canvas.fill_rect(
+            &crate::graphics_engine::Rect::new(1, 0, 1, 1),
+            Color::rgb(0, 255, 0),
+        );
+        framebuffer.present(&canvas).unwrap();
+
+        assert_eq!(contents(&file), [0xff, 0xff, 0xe0, 0x07]);
+    }
+
+    #[test]
+    fn leaves_row_padding_alone() {
+        // 3 pixels in a 16 byte row, the last 4 bytes are padding.
+        let (mut framebuffer, file) = fake(xrgb8888(3, 2, 16), 0xaa);
+        let mut canvas = Canvas::new(3, 2);
+        canvas.fill(Color::rgb(0, 0, 0));
+        framebuffer.present(&canvas).unwrap();
+
+        let bytes = contents(&file);
+        for row in bytes.chunks(16) {
+            assert_eq!(&row[..12], [0; 12]);
+            assert_eq!(&row[12..], [0xaa; 4]);
+        }
+    }
+
+    #[test]
+    fn starts_at_the_panned_offset() {
+        let info = FramebufferInfo {
+            offset: 8,
+            ..xrgb8888(2, 1, 8)
+        };
+        let (mut framebuffer, file) = fake(info, 0xaa);
+        let mut canvas = Canvas::new(2, 1);
+        canvas.fill(Color::rgb(0, 0, 0));
+        framebuffer.present(&canvas).unwrap();
+
+        let bytes = contents(&file);
+        assert_eq!(&bytes[..8], [0xaa; 8]);
+        assert_eq!(&bytes[8..], [0; 8]);
+    }
+
+    #[test]
+    fn transparent_pixels_are_blended_over_black() {
+        let (mut framebuffer, file) = fake(xrgb8888(1, 1, 4), 0xaa);
+        let mut canvas = Canvas::new(1, 1);
+        canvas.fill(Color::rgba(200, 100, 50, 0));
+        framebuffer.present(&canvas).unwrap();
+
+        assert_eq!(contents(&file), [0; 4]);
+    }
+
+    #[test]
+    fn only_changed_rows_are_written() {
+        let (mut framebuffer, file) = fake(xrgb8888(1, 2, 4), 0);
+        let mut canvas = Canvas::new(1, 2);
+        canvas.fill(Color::rgb(0, 0, 0));
+        framebuffer.present(&canvas).unwrap();
+
+        // Something else draws on the device, as the console would.
+        file.write_all_at(&[0xaa; 8], 0).unwrap();
+        canvas.fill_rect(
+            &crate::graphics_engine::Rect::new(0, 1, 1, 1),
+            Color::rgb(0, 0, 255),
+        );
+        framebuffer.present(&canvas).unwrap();
+
+        assert_eq!(contents(&file), [0xaa, 0xaa, 0xaa, 0xaa, 0xff, 0, 0, 0]);
+    }
+
+    #[test]
+    fn first_frame_is_written_even_if_black() {
+        let (mut framebuffer, file) = fake(xrgb8888(1, 1, 4), 0xaa);
+        let mut canvas = Canvas::new(1, 1);
+        canvas.fill(Color::rgb(0, 0, 0));
+        framebuffer.present(&canvas).unwrap();
+
+        assert_eq!(contents(&file), [0; 4]);
+    }
+
+    #[test]
+    fn key_names_match_sdl() {
+        assert_eq!(key_name(KeyCode::KEY_ESC).as_deref(), Some("Escape"));
+        assert_eq!(key_name(KeyCode::KEY_ENTER).as_deref(), Some("Return"));
+        assert_eq!(key_name(KeyCode::KEY_F11).as_deref(), Some("F11"));
+        assert_eq!(key_name(KeyCode::KEY_Q).as_deref(), Some("Q"));
+        assert_eq!(key_name(KeyCode::KEY_LEFTCTRL), None);
+    }
+
+    #[test]
+    fn keys_carry_held_modifiers() {
+        let (mut framebuffer, _) = fake(xrgb8888(1, 1, 4), 0);
+        assert!(framebuffer.key_event(KeyCode::KEY_LEFTSHIFT, 1).is_none());
+        let Some(SurfaceEvent::Key(combo)) = framebuffer.key_event(KeyCode::KEY_Q, 1) else {
+            panic!("expected a key");
+        };
+        let shift = Modifiers {
+            shift: true,
+            ..Modifiers::default()
+        };
+        assert_eq!(combo, KeyCombo::new("q", shift));
+
+        framebuffer.key_event(KeyCode::KEY_LEFTSHIFT, 0);
+        assert!(framebuffer.key_event(KeyCode::KEY_LEFTCTRL, 1).is_none());
+        assert!(matches!(
+            framebuffer.key_event(KeyCode::KEY_C, 1),
+            Some(SurfaceEvent::Quit)
+        ));
+    }
+}
@@ -1,5 +1,78 @@
-use crate::config::{ClockSettings, RgbColor};
+use crate::config::{
+    Action, AmPmPlacement, AmPmStyle, Anchor, AnimationStyle, Arrangement, ClockSettings,
+    FontConfig, RgbColor,
+};
+use crate::error::FlipClockError;
+use crate::export;
+use crate::font;
+use std::path::Path;
 
+/// Size of a card relative to the shorter side of the drawable in the
+/// original layout, which the scales below were tuned for.
+pub const RECT_SIZE_SCALE: f32 = 0.65;
+/// Size of the digits relative to the card they are drawn on.
+pub const FONT_SIZE_SCALE: f32 = 0.55 / RECT_SIZE_SCALE;
+/// Size of the AM/PM label relative to the card it is drawn on.
+pub const MODE_FONT_SIZE_SCALE: f32 = 1.0 / (16.5 * RECT_SIZE_SCALE);
+/// Size of labels such as the date relative to the card they are drawn on.
+pub const LABEL_FONT_SIZE_SCALE: f32 = 0.2;
+
+/// The kinds of text on the clock face, each with its own `FontConfig`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FontRole {
+    Digit,
+    Indicator,
+    Label,
+}
+
+impl FontRole {
+    /// The kinds of text the engines currently draw and load fonts for.
+    pub const DRAWN: [FontRole; 3] = [FontRole::Digit, FontRole::Indicator, FontRole::Label];
+
+    pub fn config(self, settings: &ClockSettings) -> &FontConfig {
+        match self {
+            FontRole::Digit => &settings.digit_font,
+            FontRole::Indicator => &settings.indicator_font,
+            FontRole::Label => &settings.label_font,
+        }
+    }
+
+    /// Font size in pixels for cards of `rect_size`.
+    pub fn size(self, rect_size: u32, settings: &ClockSettings) -> u32 {
+        let scale = match self {
+            FontRole::Digit => FONT_SIZE_SCALE,
+            FontRole::Indicator => MODE_FONT_SIZE_SCALE,
+            FontRole::Label => LABEL_FONT_SIZE_SCALE,
+        };
+        ((rect_size as f32 * scale * self.config(settings).size_scale) as u32).max(1)
+    }
+
+    /// The font file for this text, or one that has glyphs for all of its
+    /// characters when that one lacks some.
+    pub fn file(self, settings: &ClockSettings) -> Result<String, FlipClockError> {
+        let file = settings.font_file(self.config(settings))?;
+        let characters = match self {
+            FontRole::Digit => settings.numerals.characters(),
+            FontRole::Indicator => settings.am_pm_labels.concat(),
+            FontRole::Label => String::new(),
+        };
+        Ok(font::covering(file, &characters))
+    }
+
+    /// How far text of `size` is moved down from where it would be drawn.
+    pub fn vertical_offset(self, size: u32, settings: &ClockSettings) -> i32 {
+        (self.config(settings).vertical_offset * size as f32).round() as i32
+    }
+
+    pub fn color(self, settings: &ClockSettings) -> RgbColor {
+        match self {
+            FontRole::Indicator => settings.indicator_color.unwrap_or(settings.font_color),
+            FontRole::Digit | FontRole::Label => settings.font_color,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Rect {
     pub x: i32,
     pub y: i32,
@@ -16,9 +89,164 @@ impl Rect {
             height,
         }
     }
+
+    /// Linear interpolation towards `other`, `t` going from 0.0 (self) to 1.0 (other).
+    pub fn lerp(&self, other: &Rect, t: f32) -> Rect {
+        let mix = |from: f32, to: f32| from + (to - from) * t.clamp(0.0, 1.0);
+        Rect::new(
+            mix(self.x as f32, other.x as f32).round() as i32,
+            mix(self.y as f32, other.y as f32).round() as i32,
+            mix(self.width as f32, other.width as f32).round() as u32,
+            mix(self.height as f32, other.height as f32).round() as u32,
+        )
+    }
+
+    /// The smallest rect covering both.
+    pub fn union(&self, other: &Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width as i32).max(other.x + other.width as i32);
+        let bottom = (self.y + self.height as i32).max(other.y + other.height as i32);
+        Rect::new(x, y, (right - x) as u32, (bottom - y) as u32)
+    }
+}
+
+/// The row of a line of digits the card gap cuts through: halfway between the
+/// top of the tallest digit and the bottom of the lowest, both measured from
+/// the top of the line, then moved by the digit font's `vertical_offset`.
+pub fn digit_split(ink_top: f32, ink_bottom: f32, size: u32, settings: &ClockSettings) -> i32 {
+    ((ink_top + ink_bottom) / 2.0).round() as i32 - FontRole::Digit.vertical_offset(size, settings)
 }
 
-#[derive(Clone, Copy, Debug)]
+/// Part of a rendered line of text, `src`, and where it lands on a card,
+/// `dst`. Both have the same size unless the text is scaled down to fit the
+/// card or the flap is being folded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Flap {
+    pub src: Rect,
+    pub dst: Rect,
+}
+
+impl Flap {
+    /// The halves of a `width` x `height` line of digits on the card `rect`,
+    /// cut at row `split`: the top half ends at the card gap and the bottom
+    /// half starts below it, each cut off where its half of the card ends.
+    /// The text is drawn at `scale` of its size, less when it is wider than
+    /// the card, such as long Roman numerals.
+    pub fn halves(
+        rect: &Rect,
+        gap: i32,
+        width: u32,
+        height: u32,
+        split: i32,
+        scale: f32,
+    ) -> [Flap; 2] {
+        let top_half_height = (rect.height as i32 - gap) / 2;
+        let bottom_half_height = rect.height as i32 - top_half_height - gap;
+        let scale = (rect.width as f32 / width.max(1) as f32)
+            .min(scale)
+            .min(1.0);
+        let scaled = |length: i32| (length as f32 * scale).round() as i32;
+        let unscaled = |length: i32| (length as f32 / scale) as i32;
+        let dst_width = scaled(width as i32) as u32;
+        let x = rect.x + (rect.width as i32 - dst_width as i32) / 2;
+        let split = split.clamp(0, height as i32);
+
+        let top_height = split.min(unscaled(top_half_height)).max(0);
+        let bottom_height = (height as i32 - split)
+            .min(unscaled(bottom_half_height))
+            .max(0);
+        [
+            Flap {
+                src: Rect::new(0, split - top_height, width, top_height as u32),
+                dst: Rect::new(
+                    x,
+                    rect.y + top_half_height - scaled(top_height),
+                    dst_width,
+                    scaled(top_height) as u32,
+                ),
+            },
+            Flap {
+                src: Rect::new(0, split, width, bottom_height as u32),
+                dst: Rect::new(
+                    x,
+                    rect.y + top_half_height + gap,
+                    dst_width,
+                    scaled(bottom_height) as u32,
+                ),
+            },
+        ]
+    }
+
+    /// A whole `width` x `height` line of text in the middle of the card
+    /// `rect`, scaled down to fit between its rounded corners, and moved
+    /// down by `y_offset`.
+    pub fn centred(rect: &Rect, width: u32, height: u32, y_offset: i32) -> Flap {
+        let room = rect.width.saturating_sub(rect.height / 5);
+        let scale = (room as f32 / width.max(1) as f32)
+            .min(rect.height as f32 / height.max(1) as f32)
+            .min(1.0);
+        let (dst_width, dst_height) = (
+            (width as f32 * scale).round() as u32,
+            (height as f32 * scale).round() as u32,
+        );
+        Flap {
+            src: Rect::new(0, 0, width, height),
+            dst: Rect::new(
+                rect.x + (rect.width as i32 - dst_width as i32) / 2,
+                rect.y + (rect.height as i32 - dst_height as i32) / 2 + y_offset,
+                dst_width,
+                dst_height,
+            ),
+        }
+    }
+
+    pub fn offset(self, y_offset: i32) -> Flap {
+        Flap {
+            src: self.src,
+            dst: Rect::new(
+                self.dst.x,
+                self.dst.y + y_offset,
+                self.dst.width,
+                self.dst.height,
+            ),
+        }
+    }
+
+    /// The rows of the flap that land between `top` and `bottom` on the card.
+    pub fn clipped(self, top: i32, bottom: i32) -> Flap {
+        let dst_top = self.dst.y.max(top);
+        let dst_bottom = (self.dst.y + self.dst.height as i32)
+            .min(bottom)
+            .max(dst_top);
+        let height = (dst_bottom - dst_top) as u32;
+        let ratio = self.src.height as f32 / self.dst.height.max(1) as f32;
+        Flap {
+            src: Rect::new(
+                self.src.x,
+                self.src.y + ((dst_top - self.dst.y) as f32 * ratio).round() as i32,
+                self.src.width,
+                (height as f32 * ratio).round() as u32,
+            ),
+            dst: Rect::new(self.dst.x, dst_top, self.dst.width, height),
+        }
+    }
+
+    /// The flap squashed to `scale` of its height towards `hinge_y`, as it
+    /// turns around the hinge.
+    pub fn folded(self, hinge_y: i32, scale: f32) -> Flap {
+        let scale = scale.clamp(0.0, 1.0);
+        let fold = |y: i32| hinge_y + ((y - hinge_y) as f32 * scale).round() as i32;
+        let top = fold(self.dst.y);
+        let bottom = fold(self.dst.y + self.dst.height as i32);
+        Flap {
+            src: self.src,
+            dst: Rect::new(self.dst.x, top, self.dst.width, (bottom - top) as u32),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -45,7 +273,7 @@ impl Color {
     }
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum TimeDigitPosition {
     Hour,
     Minute,
@@ -53,15 +281,63 @@ pub enum TimeDigitPosition {
 }
 
 pub struct AnimationState {
+    pub style: AnimationStyle,
     pub current_value: u32,
     pub previous_value: Option<u32>,
+    /// Values passed between `previous_value` and `current_value`, in order.
+    /// Only filled in for styles that show them (roll and rattle).
+    pub intermediate_values: Vec<u32>,
     pub is_animating: bool,
     pub progress: f32,
 }
 
+impl AnimationState {
+    /// Every value the card shows during the transition, from the previous
+    /// value to the current one.
+    pub fn sequence(&self) -> Vec<u32> {
+        let mut values = Vec::with_capacity(self.intermediate_values.len() + 2);
+        values.extend(self.previous_value);
+        values.extend(self.intermediate_values.iter().copied());
+        values.push(self.current_value);
+        values
+    }
+
+    /// The pair of consecutive values being transitioned at the current
+    /// progress, together with the progress of that single step.
+    pub fn current_step(&self) -> (u32, u32, f32) {
+        let values = self.sequence();
+        if values.len() < 2 {
+            return (self.current_value, self.current_value, 1.0);
+        }
+
+        let steps = (values.len() - 1) as f32;
+        let position = self.progress.clamp(0.0, 1.0) * steps;
+        let index = (position.floor() as usize).min(values.len() - 2);
+        (values[index], values[index + 1], position - index as f32)
+    }
+}
+
+/// Cubic ease-in-out used by the card animations.
+pub fn easing_function(t: f32) -> f32 {
+    let t = t * 2.0;
+    if t < 1.0 {
+        0.5 * t * t * t
+    } else {
+        let t = t - 2.0;
+        0.5 * (t * t * t + 2.0)
+    }
+}
+
+/// A frame read back from an engine, as 8 bit RGBA rows without padding.
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
 pub trait GraphicsEngine {
-    fn clear(&mut self) -> Result<(), String>;
-    fn present(&mut self) -> Result<(), String>;
+    fn clear(&mut self) -> Result<(), FlipClockError>;
+    fn present(&mut self) -> Result<(), FlipClockError>;
 
     fn draw_rect(
         &mut self,
@@ -71,7 +347,7 @@ pub trait GraphicsEngine {
         border_width: u32,
         radius: i32,
         filled: bool,
-    ) -> Result<(), String>;
+    ) -> Result<(), FlipClockError>;
 
     fn render_digit(
         &mut self,
@@ -79,22 +355,565 @@ pub trait GraphicsEngine {
         position: TimeDigitPosition,
         rect: &Rect,
         animation: Option<AnimationState>,
-    ) -> Result<(), String>;
+    ) -> Result<(), FlipClockError>;
 
-    fn render_am_pm_indicator(&mut self, rect: &Rect, is_pm: bool) -> Result<(), String>;
+    /// Draws a card showing the date as `text`.
+    fn render_date(&mut self, rect: &Rect, text: &str) -> Result<(), FlipClockError>;
 
-    fn handle_events(&mut self) -> Result<bool, String>;
+    /// Draws the AM/PM indicator for the hour card `hour_rect` of a row of
+    /// cards spanning `cards`, see `am_pm_position`.
+    fn render_am_pm_indicator(
+        &mut self,
+        hour_rect: &Rect,
+        cards: &Rect,
+        is_pm: bool,
+    ) -> Result<(), FlipClockError>;
+
+    /// Actions triggered by input since the last call, in the order they happened.
+    fn handle_events(&mut self) -> Result<Vec<Action>, FlipClockError>;
 
     fn calculate_layout(&self) -> ClockLayout;
 
     fn get_settings(&self) -> &ClockSettings;
+
+    /// Replaces the settings at runtime, e.g. after a theme switch or a toggle.
+    fn update_settings(&mut self, settings: ClockSettings) -> Result<(), FlipClockError>;
+
+    /// What has been drawn since the last `clear`. Called before `present`,
+    /// after which a GPU's back buffer is undefined.
+    fn read_pixels(&mut self) -> Result<Frame, FlipClockError> {
+        Err(FlipClockError::Render(
+            "Reading back frames is not supported by this graphics engine".to_string(),
+        ))
+    }
+
+    /// Saves what has been drawn since the last `clear` as a PNG.
+    fn save_screenshot(&mut self, path: &Path) -> Result<(), FlipClockError> {
+        export::write_png(path, &self.read_pixels()?)
+    }
 }
 
 pub struct ClockLayout {
     pub hour_rect: Rect,
     pub minute_rect: Rect,
     pub second_rect: Option<Rect>,
-    pub is_horizontal: bool,
+    pub date_rect: Option<Rect>,
+    /// The arrangement used, never `Auto`.
+    pub arrangement: Arrangement,
+    /// Height of the hour and minute cards, which text is sized for.
     pub rect_size: u32,
     pub spacing: i32,
 }
+
+impl ClockLayout {
+    /// Places the cards in a `width` x `height` drawable, in pixels, leaving
+    /// room for an AM/PM indicator placed outside them.
+    pub fn calculate(width: u32, height: u32, settings: &ClockSettings) -> Self {
+        let layout = Self::place(width, height, settings);
+        if settings.use_24hour || !settings.am_pm_placement.is_outside() {
+            return layout;
+        }
+        // The cards are placed in what is left once the indicator has its room.
+        let (indicator_width, indicator_height) = am_pm_extent(layout.rect_size, settings);
+        let (indicator_width, indicator_height) =
+            (indicator_width.min(width), indicator_height.min(height));
+        match settings.am_pm_placement {
+            AmPmPlacement::Left => {
+                Self::place(width.saturating_sub(indicator_width), height, settings)
+                    .offset(indicator_width as i32, 0)
+            }
+            AmPmPlacement::Right => {
+                Self::place(width.saturating_sub(indicator_width), height, settings)
+            }
+            _ => Self::place(width, height.saturating_sub(indicator_height), settings),
+        }
+    }
+
+    fn offset(mut self, dx: i32, dy: i32) -> Self {
+        for rect in [&mut self.hour_rect, &mut self.minute_rect]
+            .into_iter()
+            .chain(self.second_rect.as_mut())
+            .chain(self.date_rect.as_mut())
+        {
+            rect.x += dx;
+            rect.y += dy;
+        }
+        self
+    }
+
+    /// Every arrangement is a stack of centred rows: a single row, one card
+    /// per row, or two. The cards are as large as the margins allow.
+    fn place(width: u32, height: u32, settings: &ClockSettings) -> Self {
+        let config = &settings.layout;
+        let arrangement = match config.arrangement {
+            Arrangement::Auto if width > height => Arrangement::Horizontal,
+            Arrangement::Auto => Arrangement::Vertical,
+            arrangement => arrangement,
+        };
+        let aspect_ratio = bounded(config.card_aspect_ratio, 0.1, 10.0);
+        let seconds_scale = bounded(config.seconds_scale, 0.1, 1.0);
+        let spacing = bounded(config.spacing, 0.0, 10.0);
+        let margin = (width.min(height) as f32 * bounded(config.margin, 0.0, 0.5)) as u32;
+        let area_width = width.saturating_sub(2 * margin);
+        let area_height = height.saturating_sub(2 * margin);
+
+        // Width and height of every card in card heights, in reading order.
+        let mut cards = vec![(aspect_ratio, 1.0), (aspect_ratio, 1.0)];
+        if settings.show_seconds {
+            cards.push((aspect_ratio * seconds_scale, seconds_scale));
+        }
+        if settings.show_date {
+            cards.push((aspect_ratio, 1.0));
+        }
+        let rows: Vec<&[(f32, f32)]> = match arrangement {
+            Arrangement::Vertical => cards.chunks(1).collect(),
+            Arrangement::Grid => cards.chunks(2).collect(),
+            _ => vec![&cards[..]],
+        };
+
+        // Pixel sizes are rounded down one by one, so their sum never outgrows the area.
+        let units = block_size(&rows, spacing, |length| length);
+        let scale = (area_width as f32 / units.0).min(area_height as f32 / units.1);
+        let pixels = |length: f32| (length * scale).floor();
+        let (block_width, block_height) = block_size(&rows, spacing, pixels);
+
+        let (align_x, align_y) = alignment(config.alignment);
+        let left = margin as f32 + (area_width as f32 - block_width) * align_x;
+        let mut y = margin as f32 + (area_height as f32 - block_height) * align_y;
+        let mut rects = Vec::new();
+        for row in &rows {
+            let (row_width, row_height) = row_size(row, spacing, pixels);
+            let mut x = left + (block_width - row_width) / 2.0;
+            for &(width, height) in row.iter() {
+                let (width, height) = (pixels(width), pixels(height));
+                let top = y + (row_height - height) / 2.0;
+                rects.push(Rect::new(x as i32, top as i32, width as u32, height as u32));
+                x += width + pixels(spacing);
+            }
+            y += row_height + pixels(spacing);
+        }
+
+        let mut rects = rects.into_iter();
+        ClockLayout {
+            hour_rect: rects.next().unwrap_or_default(),
+            minute_rect: rects.next().unwrap_or_default(),
+            second_rect: if settings.show_seconds {
+                rects.next()
+            } else {
+                None
+            },
+            date_rect: if settings.show_date {
+                rects.next()
+            } else {
+                None
+            },
+            arrangement,
+            rect_size: pixels(1.0) as u32,
+            spacing: pixels(spacing) as i32,
+        }
+    }
+}
+
+/// `value` clamped to `min..=max`, and `min` for NaN, which `clamp` keeps.
+fn bounded(value: f32, min: f32, max: f32) -> f32 {
+    if value.is_nan() {
+        min
+    } else {
+        value.clamp(min, max)
+    }
+}
+
+/// Width and height of `cards` side by side, `spacing` apart, with every
+/// length in card heights passed through `length`.
+fn row_size(cards: &[(f32, f32)], spacing: f32, length: impl Fn(f32) -> f32) -> (f32, f32) {
+    let widths: f32 = cards.iter().map(|&(width, _)| length(width)).sum();
+    let height = cards
+        .iter()
+        .map(|&(_, height)| length(height))
+        .fold(0.0, f32::max);
+    (widths + length(spacing) * (cards.len() - 1) as f32, height)
+}
+
+/// Width and height of `rows` stacked `spacing` apart, like `row_size`.
+fn block_size(
+    rows: &[&[(f32, f32)]],
+    spacing: f32,
+    length: impl Fn(f32) -> f32 + Copy,
+) -> (f32, f32) {
+    let sizes = rows.iter().map(|row| row_size(row, spacing, length));
+    let width = sizes.clone().map(|(width, _)| width).fold(0.0, f32::max);
+    let heights: f32 = sizes.map(|(_, height)| height).sum();
+    (width, heights + length(spacing) * (rows.len() - 1) as f32)
+}
+
+/// How far along the free space a block aligned to `anchor` starts, across and down.
+fn alignment(anchor: Anchor) -> (f32, f32) {
+    let x = match anchor {
+        Anchor::TopLeft | Anchor::Left | Anchor::BottomLeft => 0.0,
+        Anchor::Top | Anchor::Center | Anchor::Bottom => 0.5,
+        Anchor::TopRight | Anchor::Right | Anchor::BottomRight => 1.0,
+    };
+    let y = match anchor {
+        Anchor::TopLeft | Anchor::Top | Anchor::TopRight => 0.0,
+        Anchor::Left | Anchor::Center | Anchor::Right => 0.5,
+        Anchor::BottomLeft | Anchor::Bottom | Anchor::BottomRight => 1.0,
+    };
+    (x, y)
+}
+
+/// Space between the AM/PM indicator and the edge of the hour card `rect`.
+fn am_pm_margin(rect: &Rect) -> i32 {
+    (rect.height / 48) as i32
+}
+
+/// Diameter of the dot style indicator for an indicator font of `size`.
+pub fn am_pm_dot_size(size: u32) -> u32 {
+    (size * 2 / 3).max(2)
+}
+
+/// The room an indicator takes next to cards of `rect_size`, margins
+/// included. Text is estimated from its length as no fonts are at hand.
+fn am_pm_extent(rect_size: u32, settings: &ClockSettings) -> (u32, u32) {
+    let size = FontRole::Indicator.size(rect_size, settings);
+    let (width, height) = match settings.am_pm_style {
+        AmPmStyle::Text => {
+            let length = settings
+                .am_pm_labels
+                .iter()
+                .map(|label| label.chars().count())
+                .max()
+                .unwrap_or(0);
+            ((length as f32 * size as f32 * 0.7) as u32, size * 6 / 5)
+        }
+        AmPmStyle::Dot => (am_pm_dot_size(size), am_pm_dot_size(size)),
+    };
+    let margins = 2 * (rect_size / 48);
+    (width + margins, height + margins)
+}
+
+/// Top left corner of a `width` x `height` AM/PM indicator for the hour card
+/// `hour_rect` of a row of cards spanning `cards`, `None` when it is hidden.
+pub fn am_pm_position(
+    settings: &ClockSettings,
+    hour_rect: &Rect,
+    cards: &Rect,
+    is_pm: bool,
+    width: u32,
+    height: u32,
+) -> Option<(i32, i32)> {
+    let margin = am_pm_margin(hour_rect);
+    let (width, height) = (width as i32, height as i32);
+    let centre_x = |rect: &Rect| rect.x + (rect.width as i32 - width) / 2;
+    let top = hour_rect.y + margin;
+    let bottom = hour_rect.y + hour_rect.height as i32 - height - margin;
+    let level = hour_rect.y + (hour_rect.height as i32 - height) / 2;
+    match settings.am_pm_placement {
+        AmPmPlacement::Inside => Some((centre_x(hour_rect), if is_pm { bottom } else { top })),
+        AmPmPlacement::InsideTop => Some((centre_x(hour_rect), top)),
+        AmPmPlacement::InsideBottom => Some((centre_x(hour_rect), bottom)),
+        AmPmPlacement::Left => Some((cards.x - margin - width, level)),
+        AmPmPlacement::Right => Some((cards.x + cards.width as i32 + margin, level)),
+        AmPmPlacement::Below => Some((centre_x(cards), cards.y + cards.height as i32 + margin)),
+        AmPmPlacement::Hidden => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LayoutConfig;
+
+    const SIZES: [(u32, u32); 9] = [
+        (0, 0),
+        (1, 1),
+        (37, 300),
+        (240, 480),
+        (480, 240),
+        (1080, 1920),
+        (1920, 1080),
+        (3000, 200),
+        (200, 3000),
+    ];
+
+    fn cards(layout: &ClockLayout) -> Vec<Rect> {
+        [layout.hour_rect, layout.minute_rect]
+            .into_iter()
+            .chain(layout.second_rect)
+            .chain(layout.date_rect)
+            .collect()
+    }
+
+    fn overlap(a: &Rect, b: &Rect) -> bool {
+        a.x < b.x + b.width as i32
+            && b.x < a.x + a.width as i32
+            && a.y < b.y + b.height as i32
+            && b.y < a.y + a.height as i32
+    }
+
+    fn inside(rect: &Rect, width: u32, height: u32) -> bool {
+        rect.x >= 0
+            && rect.y >= 0
+            && rect.x + rect.width as i32 <= width as i32
+            && rect.y + rect.height as i32 <= height as i32
+    }
+
+    /// Every combination of the layout options worth telling apart.
+    fn all_settings() -> Vec<ClockSettings> {
+        let mut all = Vec::new();
+        for arrangement in [
+            Arrangement::Auto,
+            Arrangement::Horizontal,
+            Arrangement::Vertical,
+            Arrangement::Grid,
+        ] {
+            for card_aspect_ratio in [0.5, 1.0, 2.5] {
+                for seconds_scale in [0.3, 1.0] {
+                    for margin in [0.0, 0.175, 0.5] {
+                        for alignment in [Anchor::TopLeft, Anchor::Center, Anchor::BottomRight] {
+                            for (show_seconds, show_date) in
+                                [(false, false), (true, false), (false, true), (true, true)]
+                            {
+                                for am_pm_placement in [
+                                    AmPmPlacement::Inside,
+                                    AmPmPlacement::Left,
+                                    AmPmPlacement::Right,
+                                    AmPmPlacement::Below,
+                                ] {
+                                    all.push(ClockSettings {
+                                        show_seconds,
+                                        show_date,
+                                        use_24hour: false,
+                                        am_pm_placement,
+                                        layout: LayoutConfig {
+                                            arrangement,
+                                            margin,
+                                            spacing: 0.09,
+                                            card_aspect_ratio,
+                                            seconds_scale,
+                                            alignment,
+                                        },
+                                        ..ClockSettings::default()
+                                    });
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        all
+    }
+
+    #[test]
+    fn cards_never_overlap_or_leave_the_window() {
+        for settings in all_settings() {
+            for (width, height) in SIZES {
+                let layout = ClockLayout::calculate(width, height, &settings);
+                let cards = cards(&layout);
+                for (index, card) in cards.iter().enumerate() {
+                    assert!(
+                        inside(card, width, height),
+                        "{card:?} leaves {width}x{height} with {:?}",
+                        settings.layout
+                    );
+                    for other in &cards[index + 1..] {
+                        assert!(
+                            !overlap(card, other),
+                            "{card:?} overlaps {other:?} in {width}x{height} with {:?}",
+                            settings.layout
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dot_indicator_beside_the_cards_stays_in_the_window() {
+        for mut settings in all_settings() {
+            if !settings.am_pm_placement.is_outside() {
+                continue;
+            }
+            settings.am_pm_style = AmPmStyle::Dot;
+            for (width, height) in [(480, 240), (240, 480), (1920, 1080)] {
+                let layout = ClockLayout::calculate(width, height, &settings);
+                let cards = cards(&layout);
+                let row = cards
+                    .iter()
+                    .fold(layout.hour_rect, |row, card| row.union(card));
+                let dot = am_pm_dot_size(FontRole::Indicator.size(layout.rect_size, &settings));
+                let (x, y) =
+                    am_pm_position(&settings, &layout.hour_rect, &row, true, dot, dot).unwrap();
+                let dot = Rect::new(x, y, dot, dot);
+                assert!(
+                    inside(&dot, width, height),
+                    "{dot:?} leaves {width}x{height}"
+                );
+                assert!(cards.iter().all(|card| !overlap(card, &dot)));
+            }
+        }
+    }
+
+    #[test]
+    fn default_cards_keep_their_original_size() {
+        let layout = ClockLayout::calculate(480, 240, &ClockSettings::default());
+        assert_eq!(layout.arrangement, Arrangement::Horizontal);
+        assert_eq!(layout.rect_size, (240.0 * RECT_SIZE_SCALE) as u32);
+        assert_eq!(layout.hour_rect.width, layout.hour_rect.height);
+    }
+
+    #[test]
+    fn auto_arrangement_follows_the_window_shape() {
+        let settings = ClockSettings::default();
+        let tall = ClockLayout::calculate(240, 480, &settings);
+        assert_eq!(tall.arrangement, Arrangement::Vertical);
+        assert_eq!(tall.hour_rect.x, tall.minute_rect.x);
+        assert!(tall.hour_rect.y < tall.minute_rect.y);
+
+        let forced = ClockSettings {
+            layout: LayoutConfig {
+                arrangement: Arrangement::Horizontal,
+                ..LayoutConfig::default()
+            },
+            ..ClockSettings::default()
+        };
+        let wide = ClockLayout::calculate(240, 480, &forced);
+        assert_eq!(wide.hour_rect.y, wide.minute_rect.y);
+        assert!(wide.hour_rect.x < wide.minute_rect.x);
+    }
+
+    #[test]
+    fn grid_puts_seconds_and_date_beneath() {
+        let settings = ClockSettings {
+            show_seconds: true,
+            show_date: true,
+            layout: LayoutConfig {
+                arrangement: Arrangement::Grid,
+                seconds_scale: 0.5,
+                card_aspect_ratio: 1.5,
+                ..LayoutConfig::default()
+            },
+            ..ClockSettings::default()
+        };
+        let layout = ClockLayout::calculate(800, 600, &settings);
+        let second = layout.second_rect.unwrap();
+        let date = layout.date_rect.unwrap();
+        assert_eq!(layout.hour_rect.y, layout.minute_rect.y);
+        assert!(second.y > layout.hour_rect.y + layout.hour_rect.height as i32);
+        assert!(date.y >= layout.hour_rect.y + layout.hour_rect.height as i32);
+        assert!(second.x < date.x);
+        assert_eq!(second.height, layout.rect_size / 2);
+        assert_eq!(
+            layout.hour_rect.width,
+            (layout.hour_rect.height as f32 * 1.5) as u32
+        );
+    }
+
+    #[test]
+    fn alignment_moves_the_cards_to_the_edges() {
+        let aligned = |alignment| ClockSettings {
+            layout: LayoutConfig {
+                alignment,
+                margin: 0.0,
+                ..LayoutConfig::default()
+            },
+            ..ClockSettings::default()
+        };
+        let left = ClockLayout::calculate(1000, 200, &aligned(Anchor::Left));
+        assert_eq!(left.hour_rect.x, 0);
+        let right = ClockLayout::calculate(1000, 200, &aligned(Anchor::Right));
+        assert_eq!(right.minute_rect.x + right.minute_rect.width as i32, 1000);
+    }
+
+    fn bottom(rect: &Rect) -> i32 {
+        rect.y + rect.height as i32
+    }
+
+    #[test]
+    fn split_lands_at_the_ink_centre_moved_by_the_offset() {
+        let mut settings = ClockSettings::default();
+        assert_eq!(digit_split(10.0, 90.0, 100, &settings), 50);
+        settings.digit_font.vertical_offset = 0.1;
+        assert_eq!(digit_split(10.0, 90.0, 100, &settings), 40);
+        settings.digit_font.vertical_offset = -0.05;
+        assert_eq!(digit_split(10.0, 90.0, 100, &settings), 55);
+    }
+
+    #[test]
+    fn halves_stay_on_their_half_of_the_card() {
+        let rect = Rect::new(20, 30, 200, 150);
+        for gap in [0, 5, 40] {
+            let top_half = Rect::new(rect.x, rect.y, rect.width, (rect.height - gap) / 2);
+            let bottom_half = Rect::new(
+                rect.x,
+                bottom(&top_half) + gap as i32,
+                rect.width,
+                rect.height - top_half.height - gap,
+            );
+            for (width, height) in [(120, 100), (120, 400), (600, 100)] {
+                for split in [-10, 0, 30, 50, 70, 100, 500] {
+                    for scale in [0.5, 1.0] {
+                        let [top, bottom_flap] =
+                            Flap::halves(&rect, gap as i32, width, height, split, scale);
+                        for (flap, half) in [(top, top_half), (bottom_flap, bottom_half)] {
+                            if flap.dst.height == 0 {
+                                continue;
+                            }
+                            assert!(
+                                flap.dst.y >= half.y && bottom(&flap.dst) <= bottom(&half),
+                                "{flap:?} leaves {half:?}"
+                            );
+                            assert!(flap.dst.x >= rect.x && flap.dst.width <= rect.width);
+                            assert!(flap.src.y >= 0 && bottom(&flap.src) <= height as i32);
+                        }
+                        // Both halves meet the gap at the split.
+                        if top.dst.height > 0 {
+                            assert_eq!(bottom(&top.dst), bottom(&top_half));
+                            assert_eq!(bottom(&top.src), split.clamp(0, height as i32));
+                        }
+                        if bottom_flap.dst.height > 0 {
+                            assert_eq!(bottom_flap.dst.y, bottom_half.y);
+                            assert_eq!(bottom_flap.src.y, split.clamp(0, height as i32));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn text_wider_than_the_card_is_scaled_to_fit() {
+        // "VIII" in Roman numerals is about three digits wide.
+        let rect = Rect::new(0, 0, 100, 100);
+        let [top, bottom] = Flap::halves(&rect, 4, 300, 80, 40, 1.0);
+        for flap in [top, bottom] {
+            assert_eq!(flap.dst.width, 100);
+            assert_eq!(flap.dst.x, 0);
+            assert_eq!(flap.src.width, 300);
+            assert_eq!(
+                flap.dst.height,
+                (flap.src.height as f32 / 3.0).round() as u32
+            );
+        }
+
+        // Narrow text only shrinks with the card.
+        let [top, _] = Flap::halves(&rect, 4, 60, 80, 40, 0.5);
+        assert_eq!(top.dst.width, 30);
+        assert_eq!(top.dst.x, 35);
+    }
+
+    #[test]
+    fn centred_text_fits_between_the_corners() {
+        let rect = Rect::new(10, 20, 200, 100);
+        let flap = Flap::centred(&rect, 50, 30, 0);
+        assert_eq!(flap.dst, Rect::new(85, 55, 50, 30));
+        assert_eq!(flap.src, Rect::new(0, 0, 50, 30));
+        assert_eq!(Flap::centred(&rect, 50, 30, 7).dst.y, 62);
+
+        let wide = Flap::centred(&rect, 720, 60, 0);
+        assert_eq!(wide.dst.width, 180);
+        assert_eq!(wide.dst.x, 20);
+        assert_eq!(wide.dst.height, 15);
+        assert!(inside(&wide.dst, 210, 120));
+    }
+}
@@ -1,17 +1,33 @@
-use crate::config::{ClockSettings, RgbColor};
+use crate::config::{
+    Action, AmPmStyle, Anchor, AnimationStyle, ClockSettings, DisplaySelector, InputResponse,
+    MultiDisplay, RgbColor, Stacking,
+};
+use crate::error::FlipClockError;
 use crate::graphics_engine::{
-    AnimationState, ClockLayout, Color, GraphicsEngine, Rect, TimeDigitPosition,
+    AnimationState, ClockLayout, Color, Flap, FontRole, Frame, GraphicsEngine, Rect,
+    TimeDigitPosition, am_pm_dot_size, am_pm_position, digit_split, easing_function,
 };
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
-use sdl2::pixels::Color as SdlColor;
+use crate::input::{InputEvent, InputPolicy};
+use crate::keybindings::{KeyCombo, Keybindings, Modifiers};
+use crate::locale;
+use crate::x11;
+use sdl2::EventPump;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::Mod;
+use sdl2::pixels::{Color as SdlColor, PixelFormatEnum};
 use sdl2::rect::Rect as SdlRect;
-use sdl2::render::{Canvas, TextureCreator};
+use sdl2::render::{BlendMode, Canvas, Texture, TextureCreator};
+use sdl2::surface::Surface;
 use sdl2::ttf::Font;
 use sdl2::video::{FullscreenType, Window, WindowContext};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::rc::Rc;
+use std::time::Instant;
 
-const FONT_SIZE_SCALE: f32 = 0.55;
-const RECT_SIZE_SCALE: f32 = 0.65;
+/// `SDL_TOUCH_MOUSEID`, the mouse id of events synthesized from touches.
+const TOUCH_MOUSE_ID: u32 = u32::MAX;
 
 #[derive(Clone, Copy, Debug)]
 enum Quadrant {
@@ -21,47 +37,182 @@ enum Quadrant {
     BottomRight,
 }
 
+/// SDL has a single event queue. With a window per display it is polled once
+/// and every engine takes the events of its own window from here.
+struct EventQueue {
+    event_pump: EventPump,
+    pending: Vec<Event>,
+}
+
+impl EventQueue {
+    /// Events for `window_id`, plus those not tied to any window.
+    fn take_for(&mut self, window_id: u32) -> Vec<Event> {
+        self.pending.extend(self.event_pump.poll_iter());
+        let (events, others) =
+            std::mem::take(&mut self.pending)
+                .into_iter()
+                .partition(|event: &Event| {
+                    event
+                        .get_window_id()
+                        .is_none_or(|id| id == 0 || id == window_id)
+                });
+        self.pending = others;
+        events
+    }
+}
+
 pub struct Sdl2GraphicsEngine<'a> {
     sdl_context: sdl2::Sdl,
     video_subsystem: sdl2::VideoSubsystem,
-    settings: &'a ClockSettings,
+    /// The shared queue, or `None` when the host application polls SDL itself.
+    events: Option<Rc<RefCell<EventQueue>>>,
+    /// Events handed over by the host application with `push_event`.
+    forwarded_events: Vec<Event>,
+    settings: ClockSettings,
     canvas: Canvas<Window>,
     texture_creator: Option<TextureCreator<WindowContext>>,
-    time_font: Font<'a, 'a>,
-    mode_font: Font<'a, 'a>,
+    /// Files of `fonts`, looked up once since fontconfig is slow.
+    font_files: HashMap<FontRole, String>,
+    fonts: HashMap<FontRole, Font<'a, 'a>>,
+    /// Card size the fonts were loaded for, they are reloaded when it changes.
+    font_rect_size: u32,
+    /// Drawable size in pixels, which differs from the window size on high DPI displays.
+    output_size: (u32, u32),
+    /// The background alpha reaches the compositor, instead of fading the whole window.
+    per_pixel_alpha: bool,
     ttf_context: &'a sdl2::ttf::Sdl2TtfContext,
+    input_policy: InputPolicy,
+    keybindings: Keybindings,
+    started: Instant,
 }
 
 impl<'a> Sdl2GraphicsEngine<'a> {
-    pub fn new(
+    /// Opens every window `settings` asks for: one on the selected display,
+    /// one on each display, or a single one spanning all of them.
+    pub fn open_all(
         ttf_context: &'a sdl2::ttf::Sdl2TtfContext,
-        settings: &'a ClockSettings,
-    ) -> Result<Self, String> {
-        let sdl_context = sdl2::init()?;
-        let video_subsystem = sdl_context.video()?;
-
-        let mut window = video_subsystem
-            .window("Flip Clock", settings.width, settings.height)
-            .position_centered()
-            .resizable()
-            .borderless()
-            .allow_highdpi()
-            .opengl()
-            .build()
-            .map_err(|e| e.to_string())?;
-        if settings.fullscreen {
-            window.set_fullscreen(FullscreenType::Desktop)?;
-        }
+        settings: &ClockSettings,
+    ) -> Result<Vec<Self>, FlipClockError> {
+        let sdl_context = sdl2::init().map_err(FlipClockError::Video)?;
+        let video_subsystem = sdl_context.video().map_err(FlipClockError::Video)?;
+        let events = Rc::new(RefCell::new(EventQueue {
+            event_pump: sdl_context.event_pump().map_err(FlipClockError::Video)?,
+            pending: Vec::new(),
+        }));
+
+        let per_display = if settings.multi_display == MultiDisplay::Each
+            && settings.screensaver_window.is_none()
+        {
+            (0..video_subsystem
+                .num_video_displays()
+                .map_err(FlipClockError::Video)?)
+                .map(|index| {
+                    let name = video_subsystem.display_name(index).unwrap_or_default();
+                    settings.for_display(index as u32, &name)
+                })
+                .collect()
+        } else {
+            vec![settings.clone()]
+        };
 
-        // Todo figure out opacity and use the background_opacity from config here.
-        //window.set_opacity(0.0).unwrap();
+        per_display
+            .iter()
+            .map(|settings| {
+                Self::with_context(sdl_context.clone(), events.clone(), ttf_context, settings)
+            })
+            .collect()
+    }
 
-        let mut canvas = window
-            .into_canvas()
-            .present_vsync()
-            .accelerated()
+    fn with_context(
+        sdl_context: sdl2::Sdl,
+        events: Rc<RefCell<EventQueue>>,
+        ttf_context: &'a sdl2::ttf::Sdl2TtfContext,
+        settings: &ClockSettings,
+    ) -> Result<Self, FlipClockError> {
+        let video_subsystem = sdl_context.video().map_err(FlipClockError::Video)?;
+        let per_pixel_alpha = settings.transparent
+            && settings.screensaver_window.is_none()
+            && Self::request_alpha_channel(&video_subsystem);
+
+        let mut window = match settings.screensaver_window {
+            Some(window_id) => {
+                Self::foreign_window(&video_subsystem, window_id).map_err(FlipClockError::Video)?
+            }
+            None => {
+                let bounds = Self::window_bounds(&video_subsystem, settings)
+                    .map_err(FlipClockError::Video)?;
+                let mut builder =
+                    video_subsystem.window("Flip Clock", bounds.width(), bounds.height());
+                builder
+                    .position(bounds.x(), bounds.y())
+                    .resizable()
+                    .borderless()
+                    .allow_highdpi()
+                    .opengl();
+                if settings.stacking == Stacking::Above {
+                    builder.always_on_top();
+                }
+                if settings.skip_taskbar {
+                    let flags = builder.window_flags()
+                        | sdl2::sys::SDL_WindowFlags::SDL_WINDOW_SKIP_TASKBAR as u32;
+                    builder.set_window_flags(flags);
+                }
+                let mut window = builder
+                    .build()
+                    .map_err(|e| FlipClockError::Video(e.to_string()))?;
+                // Desktop fullscreen covers the display the window was placed on.
+                if settings.fullscreen && settings.multi_display != MultiDisplay::Span {
+                    window
+                        .set_fullscreen(FullscreenType::Desktop)
+                        .map_err(FlipClockError::Video)?;
+                }
+                Self::apply_window_manager_hints(&window, settings);
+                window
+            }
+        };
+
+        if settings.transparent && !per_pixel_alpha {
+            Self::set_window_opacity(&mut window, settings.background_opacity);
+        }
+
+        let mut canvas_builder = window.into_canvas().accelerated();
+        // Waiting for vblank once per window would divide the frame rate by the number of displays.
+        if settings.multi_display != MultiDisplay::Each {
+            canvas_builder = canvas_builder.present_vsync();
+        }
+        let canvas = canvas_builder
             .build()
-            .map_err(|e| e.to_string())?;
+            .map_err(|e| FlipClockError::Video(e.to_string()))?;
+        Self::with_parts(
+            sdl_context,
+            Some(events),
+            canvas,
+            per_pixel_alpha,
+            ttf_context,
+            settings,
+        )
+    }
+
+    /// Draws the clock into a canvas owned by the host application, which
+    /// keeps polling SDL itself and passes the clock's events to `push_event`.
+    pub fn with_canvas(
+        canvas: Canvas<Window>,
+        ttf_context: &'a sdl2::ttf::Sdl2TtfContext,
+        settings: &ClockSettings,
+    ) -> Result<Self, FlipClockError> {
+        let sdl_context = canvas.window().subsystem().sdl();
+        Self::with_parts(sdl_context, None, canvas, false, ttf_context, settings)
+    }
+
+    fn with_parts(
+        sdl_context: sdl2::Sdl,
+        events: Option<Rc<RefCell<EventQueue>>>,
+        mut canvas: Canvas<Window>,
+        per_pixel_alpha: bool,
+        ttf_context: &'a sdl2::ttf::Sdl2TtfContext,
+        settings: &ClockSettings,
+    ) -> Result<Self, FlipClockError> {
+        let video_subsystem = sdl_context.video().map_err(FlipClockError::Video)?;
         canvas.set_blend_mode(sdl2::render::BlendMode::Blend);
 
         canvas.set_draw_color(SdlColor::RGBA(
@@ -75,27 +226,293 @@ impl<'a> Sdl2GraphicsEngine<'a> {
         canvas.present();
 
         let texture_creator = canvas.texture_creator();
-        let time_font_size = (settings.height as f32 * FONT_SIZE_SCALE) as u16;
-        let mode_font_size = (settings.height as f32 / 16.5) as u16;
-        let time_font = ttf_context
-            .load_font(&settings.font_path, time_font_size)
-            .unwrap();
-        let mode_font = ttf_context
-            .load_font(&settings.font_path, mode_font_size)
-            .unwrap();
+        let output_size = canvas.output_size().map_err(FlipClockError::Video)?;
+        let font_rect_size =
+            ClockLayout::calculate(output_size.0, output_size.1, settings).rect_size;
+        let font_files = Self::find_fonts(settings)?;
+        let fonts = Self::load_fonts(ttf_context, settings, &font_files, font_rect_size)?;
 
         Ok(Sdl2GraphicsEngine {
             sdl_context,
             video_subsystem,
-            settings,
+            events,
+            forwarded_events: Vec::new(),
+            settings: settings.clone(),
             canvas,
             texture_creator: Some(texture_creator),
-            time_font,
-            mode_font,
+            font_files,
+            fonts,
+            font_rect_size,
+            output_size,
+            per_pixel_alpha,
             ttf_context,
+            input_policy: InputPolicy::new(settings),
+            keybindings: Keybindings::parse(&settings.keybindings)?,
+            started: Instant::now(),
         })
     }
 
+    /// Hands an event to the clock when the host application polls SDL.
+    /// It is handled on the clock's next tick.
+    pub fn push_event(&mut self, event: Event) {
+        self.forwarded_events.push(event);
+    }
+
+    /// Asks for a framebuffer with an alpha channel, returning whether the
+    /// compositor will blend it with the desktop. On X11 that takes an ARGB
+    /// visual and a running compositing manager, Wayland always composites.
+    fn request_alpha_channel(video_subsystem: &sdl2::VideoSubsystem) -> bool {
+        video_subsystem.gl_attr().set_alpha_size(8);
+        sdl2::hint::set("SDL_RENDER_DRIVER", "opengl");
+
+        match video_subsystem.current_video_driver() {
+            "wayland" => true,
+            "x11" => match x11::argb_visual_id() {
+                Some(visual_id) if x11::compositor_running() => {
+                    sdl2::hint::set("SDL_VIDEO_X11_WINDOW_VISUALID", &visual_id.to_string())
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Fallback that fades the cards along with the background.
+    fn set_window_opacity(window: &mut Window, opacity: f32) {
+        if let Err(e) = window.set_opacity(opacity.clamp(0.0, 1.0)) {
+            eprintln!("Window transparency is not supported: {}", e);
+        }
+    }
+
+    fn find_fonts(settings: &ClockSettings) -> Result<HashMap<FontRole, String>, FlipClockError> {
+        FontRole::DRAWN
+            .into_iter()
+            .map(|role| Ok((role, role.file(settings)?)))
+            .collect()
+    }
+
+    fn load_fonts(
+        ttf_context: &'a sdl2::ttf::Sdl2TtfContext,
+        settings: &ClockSettings,
+        font_files: &HashMap<FontRole, String>,
+        rect_size: u32,
+    ) -> Result<HashMap<FontRole, Font<'a, 'a>>, FlipClockError> {
+        font_files
+            .iter()
+            .map(|(&role, file)| {
+                let size = role.size(rect_size, settings).min(u16::MAX as u32) as u16;
+                let font = ttf_context
+                    .load_font(file, size)
+                    .map_err(|e| FlipClockError::font(file, e))?;
+                Ok((role, font))
+            })
+            .collect()
+    }
+
+    /// `text` in the font for `role`, spaced out as configured. SDL_ttf knows
+    /// nothing of letter spacing, so spaced text is put together glyph by glyph.
+    fn render_text(&self, role: FontRole, text: &str) -> Result<Surface<'static>, String> {
+        let font = &self.fonts[&role];
+        let config = role.config(&self.settings);
+        let color = self.settings_color_to_sdl_color(role.color(&self.settings));
+        if config.letter_spacing == 0.0 && !config.tabular_figures {
+            return font.render(text).blended(color).map_err(|e| e.to_string());
+        }
+
+        let advance = |c| {
+            font.find_glyph_metrics(c)
+                .map_or(0, |metrics| metrics.advance)
+        };
+        // Cells as wide as the digits of the numerals in use, not just the Western ones.
+        let figures = self.settings.numerals.digits();
+        let figure_width = config
+            .tabular_figures
+            .then(|| figures.chars().map(advance).max().unwrap_or(0));
+        let spacing = (config.letter_spacing
+            * role.size(self.font_rect_size, &self.settings) as f32)
+            .round() as i32;
+
+        let mut glyphs = Vec::new();
+        let mut x = 0;
+        for (index, c) in text.chars().enumerate() {
+            if index > 0 {
+                x += spacing;
+            }
+            let glyph = font
+                .render_char(c)
+                .blended(color)
+                .map_err(|e| e.to_string())?;
+            match figure_width.filter(|_| figures.contains(c)) {
+                Some(figure_width) => {
+                    glyphs.push((x + (figure_width - advance(c)) / 2, glyph));
+                    x += figure_width;
+                }
+                None => {
+                    glyphs.push((x, glyph));
+                    x += advance(c);
+                }
+            }
+        }
+
+        let mut surface = Surface::new(
+            x.max(1) as u32,
+            font.height().max(1) as u32,
+            PixelFormatEnum::ARGB8888,
+        )?;
+        for (x, mut glyph) in glyphs {
+            // Copied as is, blending onto the transparent surface would darken the edges.
+            glyph.set_blend_mode(BlendMode::None)?;
+            let dst = SdlRect::new(x, 0, glyph.width(), glyph.height());
+            glyph.blit(None, &mut surface, dst)?;
+        }
+        Ok(surface)
+    }
+
+    /// Picks up the real drawable size after a resize, a fullscreen toggle or
+    /// a move to a display with a different scale, and reloads the fonts at
+    /// the new pixel size so the digits stay crisp.
+    fn refresh_output_size(&mut self) -> Result<(), FlipClockError> {
+        self.output_size = self.canvas.output_size().map_err(FlipClockError::Video)?;
+        let rect_size = self.calculate_layout().rect_size;
+        if rect_size != self.font_rect_size {
+            self.fonts = Self::load_fonts(
+                self.ttf_context,
+                &self.settings,
+                &self.font_files,
+                rect_size,
+            )?;
+            self.font_rect_size = rect_size;
+        }
+        Ok(())
+    }
+
+    /// Where the window goes: centered on the selected display, or across the
+    /// bounding box of all displays when spanning.
+    fn window_bounds(
+        video_subsystem: &sdl2::VideoSubsystem,
+        settings: &ClockSettings,
+    ) -> Result<SdlRect, String> {
+        let display_count = video_subsystem.num_video_displays()?;
+
+        if settings.multi_display == MultiDisplay::Span {
+            return (0..display_count)
+                .map(|index| video_subsystem.display_bounds(index))
+                .reduce(|a, b| Ok(a?.union(b?)))
+                .unwrap_or_else(|| Err("No displays found".to_string()));
+        }
+
+        let index = match &settings.display {
+            Some(selector) => Self::display_index(video_subsystem, selector, display_count)?,
+            None => 0,
+        };
+        // Usable bounds leave out panels and docks, so an anchored widget does not hide under them.
+        let area = video_subsystem.display_usable_bounds(index)?;
+        let width = settings.width.min(area.width());
+        let height = settings.height.min(area.height());
+        let (x, y) = Self::anchored_position(area, width, height, settings);
+        Ok(SdlRect::new(x, y, width, height))
+    }
+
+    /// Top left corner of a `width` x `height` window attached to `area` at
+    /// the configured anchor, moved inwards by the offsets.
+    fn anchored_position(
+        area: SdlRect,
+        width: u32,
+        height: u32,
+        settings: &ClockSettings,
+    ) -> (i32, i32) {
+        let free_x = (area.width() - width) as i32;
+        let free_y = (area.height() - height) as i32;
+        let x = match settings.anchor {
+            Anchor::TopLeft | Anchor::Left | Anchor::BottomLeft => settings.offset_x,
+            Anchor::TopRight | Anchor::Right | Anchor::BottomRight => free_x - settings.offset_x,
+            Anchor::Top | Anchor::Center | Anchor::Bottom => free_x / 2 + settings.offset_x,
+        };
+        let y = match settings.anchor {
+            Anchor::TopLeft | Anchor::Top | Anchor::TopRight => settings.offset_y,
+            Anchor::BottomLeft | Anchor::Bottom | Anchor::BottomRight => free_y - settings.offset_y,
+            Anchor::Left | Anchor::Center | Anchor::Right => free_y / 2 + settings.offset_y,
+        };
+        (area.x() + x, area.y() + y)
+    }
+
+    /// Window manager hints SDL has no flag for. They are best effort, a
+    /// window manager that ignores them leaves a working but ordinary window.
+    fn apply_window_manager_hints(window: &Window, settings: &ClockSettings) {
+        if settings.stacking != Stacking::Below && !settings.click_through {
+            return;
+        }
+        let Some(window_id) = Self::x11_window_id(window) else {
+            eprintln!("Always-below and click-through windows are only supported on X11");
+            return;
+        };
+
+        if settings.stacking == Stacking::Below
+            && let Err(e) = x11::add_window_state(window_id, "_NET_WM_STATE_BELOW")
+        {
+            eprintln!("Failed to keep the window below others: {}", e);
+        }
+        if settings.click_through
+            && let Err(e) = x11::set_click_through(window_id)
+        {
+            eprintln!("Failed to make the window click-through: {}", e);
+        }
+    }
+
+    /// The X11 id of a window SDL created, `None` under other video drivers.
+    fn x11_window_id(window: &Window) -> Option<u64> {
+        use sdl2::sys::{SDL_SYSWM_TYPE, SDL_bool, SDL_version};
+
+        let mut info: sdl2::sys::SDL_SysWMinfo = unsafe { std::mem::zeroed() };
+        info.version = SDL_version {
+            major: sdl2::sys::SDL_MAJOR_VERSION as u8,
+            minor: sdl2::sys::SDL_MINOR_VERSION as u8,
+            patch: sdl2::sys::SDL_PATCHLEVEL as u8,
+        };
+        let found = unsafe { sdl2::sys::SDL_GetWindowWMInfo(window.raw(), &mut info) };
+        if found != SDL_bool::SDL_TRUE || info.subsystem != SDL_SYSWM_TYPE::SDL_SYSWM_X11 {
+            return None;
+        }
+        #[allow(clippy::unnecessary_cast)] // `Window` is a `c_ulong`, only 32 bits on some targets.
+        Some(unsafe { info.info.x11.window } as u64)
+    }
+
+    fn display_index(
+        video_subsystem: &sdl2::VideoSubsystem,
+        selector: &DisplaySelector,
+        display_count: i32,
+    ) -> Result<i32, String> {
+        (0..display_count)
+            .find(|&index| {
+                let name = video_subsystem.display_name(index).unwrap_or_default();
+                selector.matches(index as u32, &name)
+            })
+            .ok_or_else(|| format!("No display matches {}", selector))
+    }
+
+    fn modifiers(keymod: Mod) -> Modifiers {
+        Modifiers {
+            ctrl: keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD),
+            alt: keymod.intersects(Mod::LALTMOD | Mod::RALTMOD),
+            shift: keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD),
+            logo: keymod.intersects(Mod::LGUIMOD | Mod::RGUIMOD),
+        }
+    }
+
+    /// Wraps a window owned by someone else, such as the one XScreenSaver
+    /// hands to its hacks through `-window-id` or `XSCREENSAVER_WINDOW`.
+    fn foreign_window(
+        video_subsystem: &sdl2::VideoSubsystem,
+        window_id: u64,
+    ) -> Result<Window, String> {
+        sdl2::hint::set("SDL_VIDEO_FOREIGN_WINDOW_OPENGL", "1");
+        let raw = unsafe { sdl2::sys::SDL_CreateWindowFrom(window_id as usize as *const c_void) };
+        if raw.is_null() {
+            return Err(sdl2::get_error());
+        }
+        Ok(unsafe { Window::from_ll(video_subsystem.clone(), raw, std::ptr::null_mut()) })
+    }
+
     fn to_sdl_color(&self, color: Color) -> SdlColor {
         SdlColor::RGBA(color.r, color.g, color.b, color.a)
     }
@@ -108,22 +525,8 @@ impl<'a> Sdl2GraphicsEngine<'a> {
         SdlRect::new(rect.x, rect.y, rect.width, rect.height)
     }
 
-    fn format_time(&self, time: u32) -> String {
-        if self.settings.show_leading_zero {
-            format!("{:02}", time)
-        } else {
-            format!("{}", time)
-        }
-    }
-
-    fn easing_function(t: f32) -> f32 {
-        let t = t * 2.0;
-        if t < 1.0 {
-            0.5 * t * t * t
-        } else {
-            let t = t - 2.0;
-            0.5 * (t * t * t + 2.0)
-        }
+    fn format_time(&self, time: u32, position: TimeDigitPosition) -> String {
+        locale::format_value(time, position, &self.settings)
     }
 
     fn draw_filled_quarter_circle(
@@ -371,374 +774,333 @@ impl<'a> Sdl2GraphicsEngine<'a> {
     fn render_digits(
         &mut self,
         current_time: u32,
-        past_time: Option<u32>,
+        position: TimeDigitPosition,
         rect: &Rect,
-        is_animating: bool,
-        animation_progress: f32,
+        animation: Option<&AnimationState>,
     ) -> Result<(), String> {
         let gap_height = self.settings.card_gap;
         let top_half_height = (rect.height as i32 - gap_height) / 2;
-        let bottom_half_height = rect.height as i32 - top_half_height - gap_height;
-
-        let time_str = self.format_time(current_time);
-        let past_time_str = past_time.map(|t| self.format_time(t));
 
         let card_color = self.settings_color_to_sdl_color(self.settings.card_color);
 
-        let border_color = self.settings_color_to_sdl_color(self.settings.card_border_color);
+        self.draw_card(rect)?;
+
+        match animation.filter(|a| a.is_animating && a.previous_value.is_some()) {
+            Some(animation) => match animation.style {
+                AnimationStyle::Flip | AnimationStyle::Rattle => {
+                    // Every flap gets an equal slice of the animation and flips on its own.
+                    let (from, to, step_progress) = animation.current_step();
+                    let past_time_str = self.format_time(from, position);
+                    let time_str = self.format_time(to, position);
+                    self.render_flip(&time_str, &past_time_str, rect, step_progress)?;
+                }
+                AnimationStyle::Slide | AnimationStyle::Roll => {
+                    self.render_roll(&animation.sequence(), position, rect, animation.progress)?;
+                }
+                AnimationStyle::Fade => {
+                    let (from, to, step_progress) = animation.current_step();
+                    let eased_progress = easing_function(step_progress);
+                    let alpha = (eased_progress * 255.0) as u8;
+                    let past_time_str = self.format_time(from, position);
+                    let time_str = self.format_time(to, position);
+                    self.render_static_digit(&past_time_str, rect, 0, 255 - alpha)?;
+                    self.render_static_digit(&time_str, rect, 0, alpha)?;
+                }
+                AnimationStyle::None => {
+                    let time_str = self.format_time(current_time, position);
+                    self.render_static_digit(&time_str, rect, 0, 255)?;
+                }
+            },
+            None => {
+                let time_str = self.format_time(current_time, position);
+                self.render_static_digit(&time_str, rect, 0, 255)?;
+            }
+        }
+
+        self.canvas.set_draw_color(card_color);
+        let gap_rect = Rect::new(
+            rect.x,
+            rect.y + top_half_height,
+            rect.width,
+            gap_height as u32,
+        );
+        self.canvas.fill_rect(self.to_sdl_rect(&gap_rect))?;
+
+        Ok(())
+    }
+
+    /// `time_str` rendered, with its halves on the card `rect`. The gap cuts
+    /// through the middle of the digits' glyph bounding boxes.
+    fn digit_halves(
+        &self,
+        time_str: &str,
+        rect: &Rect,
+    ) -> Result<(Surface<'static>, [Flap; 2]), String> {
+        let surface = self.render_text(FontRole::Digit, time_str)?;
+        let font = &self.fonts[&FontRole::Digit];
+        let (ink_top, ink_bottom) = self
+            .settings
+            .numerals
+            .digits()
+            .chars()
+            .filter_map(|c| font.find_glyph_metrics(c))
+            .map(|metrics| (font.ascent() - metrics.maxy, font.ascent() - metrics.miny))
+            .reduce(|(top, bottom), (glyph_top, glyph_bottom)| {
+                (top.min(glyph_top), bottom.max(glyph_bottom))
+            })
+            .unwrap_or((0, surface.height() as i32));
+        let size = FontRole::Digit.size(self.font_rect_size, &self.settings);
+        let split = digit_split(ink_top as f32, ink_bottom as f32, size, &self.settings);
+        let halves = Flap::halves(
+            rect,
+            self.settings.card_gap,
+            surface.width(),
+            surface.height(),
+            split,
+            rect.height as f32 / self.font_rect_size.max(1) as f32,
+        );
+        Ok((surface, halves))
+    }
+
+    fn copy_flap(canvas: &mut Canvas<Window>, texture: &Texture, flap: Flap) -> Result<(), String> {
+        // SDL rects are at least a pixel high, an empty flap would still show a line.
+        if flap.src.height == 0 || flap.dst.height == 0 {
+            return Ok(());
+        }
+        canvas.copy(
+            texture,
+            SdlRect::new(flap.src.x, flap.src.y, flap.src.width, flap.src.height),
+            SdlRect::new(flap.dst.x, flap.dst.y, flap.dst.width, flap.dst.height),
+        )
+    }
 
+    fn draw_card(&mut self, rect: &Rect) -> Result<(), String> {
+        let card_color = self.settings_color_to_sdl_color(self.settings.card_color);
+        let border_color = self.settings_color_to_sdl_color(self.settings.card_border_color);
         let corner_radius = if self.settings.card_rounded_corners {
             (rect.height / 10) as i32
         } else {
             0
         };
+        self.fill_rounded_rect(rect, card_color, corner_radius)?;
+        self.draw_rounded_rect_border(
+            rect,
+            border_color,
+            corner_radius,
+            self.settings.card_border_size,
+        )
+    }
 
-        self.fill_rounded_rect(&rect, card_color, 66)?;
-        if self.settings.card_border_size > 0 {
-            self.draw_rounded_rect_border(&rect, border_color, 0, 0)?;
-        }
-
-        if !is_animating {
-            {
-                let current_digit_surface = self
-                    .time_font
-                    .render(&time_str)
-                    .blended(self.settings_color_to_sdl_color(self.settings.font_color))
-                    .map_err(|e| e.to_string())?;
+    /// A card with `text` in the label font, scaled down to fit.
+    fn render_label_card(&mut self, rect: &Rect, text: &str) -> Result<(), String> {
+        self.draw_card(rect)?;
+        let surface = self.render_text(FontRole::Label, text)?;
+        let texture = self
+            .texture_creator
+            .as_ref()
+            .unwrap()
+            .create_texture_from_surface(&surface)
+            .map_err(|e| e.to_string())?;
+        let size = FontRole::Label.size(self.font_rect_size, &self.settings);
+        let flap = Flap::centred(
+            rect,
+            surface.width(),
+            surface.height(),
+            FontRole::Label.vertical_offset(size, &self.settings),
+        );
+
+        self.canvas.set_clip_rect(Some(self.to_sdl_rect(rect)));
+        let result = Self::copy_flap(&mut self.canvas, &texture, flap);
+        self.canvas.set_clip_rect(None);
+        result
+    }
 
-                let current_digit_texture = self
-                    .texture_creator
-                    .as_ref()
-                    .unwrap()
-                    .create_texture_from_surface(&current_digit_surface)
-                    .map_err(|e| e.to_string())?;
+    /// Draws a digit at rest, split in two halves around the card gap.
+    /// `y_offset` shifts it vertically and the card clips whatever falls outside.
+    fn render_static_digit(
+        &mut self,
+        time_str: &str,
+        rect: &Rect,
+        y_offset: i32,
+        alpha: u8,
+    ) -> Result<(), String> {
+        let (surface, halves) = self.digit_halves(time_str, rect)?;
+        let mut texture = self
+            .texture_creator
+            .as_ref()
+            .unwrap()
+            .create_texture_from_surface(&surface)
+            .map_err(|e| e.to_string())?;
+        texture.set_alpha_mod(alpha);
+
+        self.canvas.set_clip_rect(Some(self.to_sdl_rect(rect)));
+        let result = halves.into_iter().try_for_each(|half| {
+            Self::copy_flap(&mut self.canvas, &texture, half.offset(y_offset))
+        });
+        self.canvas.set_clip_rect(None);
+        result
+    }
 
-                let texture_query = current_digit_texture.query();
-                let digit_width = texture_query.width;
-                let digit_height = texture_query.height;
-                let digit_x = rect.x + (rect.width as i32 - digit_width as i32) / 2;
-
-                let top_src_full_rect = Rect::new(0, 0, digit_width, digit_height / 2);
-                let bottom_src_full_rect =
-                    Rect::new(0, digit_height as i32 / 2, digit_width, digit_height / 2);
-
-                let top_dest_rect = Rect::new(
-                    digit_x,
-                    rect.y + (top_half_height - digit_height as i32 / 2) / 2,
-                    digit_width,
-                    top_half_height as u32,
-                );
-                let bottom_dest_rect = Rect::new(
-                    digit_x,
-                    rect.y
-                        + top_half_height
-                        + gap_height
-                        + (bottom_half_height - digit_height as i32 / 2) / 2,
-                    digit_width,
-                    bottom_half_height as u32,
-                );
-                self.canvas.copy(
-                    &current_digit_texture,
-                    self.to_sdl_rect(&top_src_full_rect),
-                    self.to_sdl_rect(&top_dest_rect),
-                )?;
-                self.canvas.copy(
-                    &current_digit_texture,
-                    self.to_sdl_rect(&bottom_src_full_rect),
-                    self.to_sdl_rect(&bottom_dest_rect),
-                )?;
+    /// Scrolls the card upwards through `values`, one card height per value,
+    /// like the drum of an odometer.
+    fn render_roll(
+        &mut self,
+        values: &[u32],
+        position: TimeDigitPosition,
+        rect: &Rect,
+        progress: f32,
+    ) -> Result<(), String> {
+        let steps = values.len().saturating_sub(1) as f32;
+        let eased_progress = easing_function(progress);
+        let scrolled = eased_progress * steps * rect.height as f32;
+
+        for (index, value) in values.iter().enumerate() {
+            let y_offset = (index as f32 * rect.height as f32 - scrolled) as i32;
+            if y_offset.unsigned_abs() >= rect.height {
+                continue;
             }
+            let time_str = self.format_time(*value, position);
+            self.render_static_digit(&time_str, rect, y_offset, 255)?;
+        }
 
-            self.canvas.set_draw_color(card_color);
-            let gap_rect = Rect::new(
-                rect.x,
-                rect.y + top_half_height,
-                rect.width,
-                gap_height as u32,
-            );
-            self.canvas.fill_rect(self.to_sdl_rect(&gap_rect))?;
-        } else if let Some(past) = past_time {
-            let eased_progress = Self::easing_function(animation_progress);
-
-            if eased_progress < 0.5 {
-                let reveal_progress = eased_progress * 2.0;
-                let revealed_height =
-                    (top_half_height as f32 * reveal_progress).min(top_half_height as f32) as u32;
+        Ok(())
+    }
 
-                {
-                    let current_digit_surface = self
-                        .time_font
-                        .render(&time_str)
-                        .blended(self.settings_color_to_sdl_color(self.settings.font_color))
-                        .map_err(|e| e.to_string())?;
-                    let current_digit_texture = self
-                        .texture_creator
-                        .as_ref()
-                        .unwrap()
-                        .create_texture_from_surface(&current_digit_surface)
-                        .map_err(|e| e.to_string())?;
-                    let texture_query = current_digit_texture.query();
-                    let digit_width = texture_query.width;
-                    let digit_x = rect.x + (rect.width as i32 - digit_width as i32) / 2;
-                    let current_top_src_rect = Rect::new(0, 0, digit_width, revealed_height);
-                    let current_top_dest_rect =
-                        Rect::new(digit_x, rect.y, digit_width, revealed_height);
-                    self.canvas.copy(
-                        &current_digit_texture,
-                        self.to_sdl_rect(&current_top_src_rect),
-                        self.to_sdl_rect(&current_top_dest_rect),
-                    )?;
-                }
+    /// The top half of the old value folds down onto the gap, then the
+    /// bottom half of the new value unfolds from it.
+    fn render_flip(
+        &mut self,
+        time_str: &str,
+        past_time_str: &str,
+        rect: &Rect,
+        animation_progress: f32,
+    ) -> Result<(), String> {
+        let gap_height = self.settings.card_gap;
+        let top_half_height = (rect.height as i32 - gap_height) / 2;
+        let bottom_half_height = rect.height as i32 - top_half_height - gap_height;
+        let gap_top = rect.y + top_half_height;
+        let bottom_y = gap_top + gap_height;
+        let card_bottom = rect.y + rect.height as i32;
+
+        let (current_surface, [current_top, current_bottom]) = self.digit_halves(time_str, rect)?;
+        let (past_surface, [past_top, past_bottom]) = self.digit_halves(past_time_str, rect)?;
+        let texture_creator = self.texture_creator.as_ref().unwrap();
+        let current = texture_creator
+            .create_texture_from_surface(&current_surface)
+            .map_err(|e| e.to_string())?;
+        let past = texture_creator
+            .create_texture_from_surface(&past_surface)
+            .map_err(|e| e.to_string())?;
 
-                {
-                    let past_time_str = past_time_str.unwrap();
-                    let past_digit_surface = self
-                        .time_font
-                        .render(&past_time_str)
-                        .blended(self.settings_color_to_sdl_color(self.settings.font_color))
-                        .map_err(|e| e.to_string())?;
-                    let past_digit_texture = self
-                        .texture_creator
-                        .as_ref()
-                        .unwrap()
-                        .create_texture_from_surface(&past_digit_surface)
-                        .map_err(|e| e.to_string())?;
-                    let texture_query = past_digit_texture.query();
-                    let digit_width = texture_query.width;
-                    let digit_x = rect.x + (rect.width as i32 - digit_width as i32) / 2;
-                    let top_src_full_rect = Rect::new(0, 0, digit_width, texture_query.height / 2);
-                    let shrink_progress = 1.0 - eased_progress * 2.0;
-                    let current_top_past_height =
-                        (top_half_height as f32 * shrink_progress).max(0.0) as u32;
-                    let top_past_dest_rect = Rect::new(
-                        digit_x,
-                        rect.y + top_half_height as i32 - current_top_past_height as i32,
-                        digit_width,
-                        current_top_past_height,
-                    );
-                    self.canvas.copy(
-                        &past_digit_texture,
-                        self.to_sdl_rect(&top_src_full_rect),
-                        self.to_sdl_rect(&top_past_dest_rect),
-                    )?;
-                }
+        let eased_progress = easing_function(animation_progress);
+        if eased_progress < 0.5 {
+            let revealed_height = (top_half_height as f32 * eased_progress * 2.0) as i32;
+            Self::copy_flap(
+                &mut self.canvas,
+                &current,
+                current_top.clipped(rect.y, rect.y + revealed_height),
+            )?;
+            Self::copy_flap(
+                &mut self.canvas,
+                &past,
+                past_top.folded(gap_top, 1.0 - eased_progress * 2.0),
+            )?;
+            Self::copy_flap(&mut self.canvas, &past, past_bottom)?;
+        } else {
+            Self::copy_flap(&mut self.canvas, &current, current_top)?;
+
+            let bottom_flip_progress = (eased_progress - 0.5) * 2.0;
+            let old_visible_height =
+                (bottom_half_height as f32 * (1.0 - bottom_flip_progress)).max(0.0) as i32;
+            Self::copy_flap(
+                &mut self.canvas,
+                &past,
+                past_bottom.clipped(card_bottom - old_visible_height, card_bottom),
+            )?;
 
-                {
-                    let past_time_str = self.format_time(past_time.clone().unwrap());
-                    let past_digit_surface = self
-                        .time_font
-                        .render(&past_time_str)
-                        .blended(self.settings_color_to_sdl_color(self.settings.font_color))
-                        .map_err(|e| e.to_string())?;
-                    let past_digit_texture = self
-                        .texture_creator
-                        .as_ref()
-                        .unwrap()
-                        .create_texture_from_surface(&past_digit_surface)
-                        .map_err(|e| e.to_string())?;
-                    let texture_query = past_digit_texture.query();
-                    let digit_width = texture_query.width;
-                    let digit_x = rect.x + (rect.width as i32 - digit_width as i32) / 2;
-                    let bottom_src_full_rect = Rect::new(
-                        0,
-                        texture_query.height as i32 / 2,
-                        digit_width,
-                        texture_query.height / 2,
-                    );
-                    let bottom_past_dest_rect = Rect::new(
-                        digit_x,
-                        rect.y + top_half_height + gap_height,
-                        digit_width,
-                        bottom_half_height as u32,
-                    );
-                    self.canvas.copy(
-                        &past_digit_texture,
-                        self.to_sdl_rect(&bottom_src_full_rect),
-                        self.to_sdl_rect(&bottom_past_dest_rect),
-                    )?;
-                }
-            } else {
-                {
-                    let current_digit_surface = self
-                        .time_font
-                        .render(&time_str)
-                        .blended(self.settings_color_to_sdl_color(self.settings.font_color))
-                        .map_err(|e| e.to_string())?;
-                    let current_digit_texture = self
-                        .texture_creator
-                        .as_ref()
-                        .unwrap()
-                        .create_texture_from_surface(&current_digit_surface)
-                        .map_err(|e| e.to_string())?;
-                    let texture_query = current_digit_texture.query();
-                    let digit_width = texture_query.width;
-                    let digit_x = rect.x + (rect.width as i32 - digit_width as i32) / 2;
-                    let top_src_full_rect = Rect::new(0, 0, digit_width, texture_query.height / 2);
-                    let top_dest_rect =
-                        Rect::new(digit_x, rect.y, digit_width, top_half_height as u32);
-                    self.canvas.copy(
-                        &current_digit_texture,
-                        self.to_sdl_rect(&top_src_full_rect),
-                        self.to_sdl_rect(&top_dest_rect),
-                    )?;
-                }
+            let new_revealed_height =
+                (bottom_half_height as f32 * bottom_flip_progress).max(0.0) as i32;
+            Self::copy_flap(
+                &mut self.canvas,
+                &current,
+                current_bottom.clipped(bottom_y, bottom_y + new_revealed_height),
+            )?;
+        }
+
+        Ok(())
+    }
 
-                let bottom_flip_progress = (eased_progress - 0.5) * 2.0;
+    fn render_am_pm(&mut self, hour_rect: &Rect, cards: &Rect, is_pm: bool) -> Result<(), String> {
+        if self.settings.use_24hour {
+            return Ok(());
+        }
+        let size = FontRole::Indicator.size(self.font_rect_size, &self.settings);
+        match self.settings.am_pm_style {
+            AmPmStyle::Text => {
+                let am_pm_text = &self.settings.am_pm_labels[is_pm as usize];
+                let am_pm_surface = self.render_text(FontRole::Indicator, am_pm_text)?;
 
+                let am_pm_texture = self
+                    .texture_creator
+                    .as_ref()
+                    .unwrap()
+                    .create_texture_from_surface(&am_pm_surface)
+                    .map_err(|e| e.to_string())?;
+
+                let texture_query = am_pm_texture.query();
+                let (width, height) = (texture_query.width, texture_query.height);
+                if let Some((x, y)) =
+                    am_pm_position(&self.settings, hour_rect, cards, is_pm, width, height)
                 {
-                    let past_time_str = past_time_str.unwrap();
-                    let past_digit_surface = self
-                        .time_font
-                        .render(&past_time_str)
-                        .blended(self.settings_color_to_sdl_color(self.settings.font_color))
-                        .map_err(|e| e.to_string())?;
-                    let past_digit_texture = self
-                        .texture_creator
-                        .as_ref()
-                        .unwrap()
-                        .create_texture_from_surface(&past_digit_surface)
-                        .map_err(|e| e.to_string())?;
-                    let texture_query = past_digit_texture.query();
-                    let digit_width = texture_query.width;
-                    let digit_x = rect.x + (rect.width as i32 - digit_width as i32) / 2;
-                    let bottom_src_full_rect = Rect::new(
-                        0,
-                        texture_query.height as i32 / 2,
-                        digit_width,
-                        texture_query.height / 2,
-                    );
-                    let old_bottom_visible_height =
-                        (bottom_half_height as f32 * (1.0 - bottom_flip_progress)).max(0.0) as u32;
-                    let old_bottom_src_rect = Rect::new(
-                        0,
-                        texture_query.height as i32 / 2
-                            + (bottom_half_height as u32 - old_bottom_visible_height) as i32,
-                        digit_width,
-                        old_bottom_visible_height,
-                    );
-                    let old_bottom_dest_rect = Rect::new(
-                        digit_x,
-                        rect.y
-                            + top_half_height
-                            + gap_height
-                            + (bottom_half_height as i32 - old_bottom_visible_height as i32),
-                        digit_width,
-                        old_bottom_visible_height,
-                    );
+                    let y = y + FontRole::Indicator.vertical_offset(size, &self.settings);
                     self.canvas.copy(
-                        &past_digit_texture,
-                        self.to_sdl_rect(&old_bottom_src_rect),
-                        self.to_sdl_rect(&old_bottom_dest_rect),
+                        &am_pm_texture,
+                        None,
+                        self.to_sdl_rect(&Rect::new(x, y, width, height)),
                     )?;
                 }
-
+            }
+            AmPmStyle::Dot if is_pm => {
+                let dot = am_pm_dot_size(size);
+                if let Some((x, y)) =
+                    am_pm_position(&self.settings, hour_rect, cards, is_pm, dot, dot)
                 {
-                    let current_digit_surface = self
-                        .time_font
-                        .render(&time_str)
-                        .blended(self.settings_color_to_sdl_color(self.settings.font_color))
-                        .map_err(|e| e.to_string())?;
-                    let current_digit_texture = self
-                        .texture_creator
-                        .as_ref()
-                        .unwrap()
-                        .create_texture_from_surface(&current_digit_surface)
-                        .map_err(|e| e.to_string())?;
-                    let texture_query = current_digit_texture.query();
-                    let digit_width = texture_query.width;
-                    let digit_x = rect.x + (rect.width as i32 - digit_width as i32) / 2;
-                    let bottom_src_full_rect = Rect::new(
-                        0,
-                        texture_query.height as i32 / 2,
-                        digit_width,
-                        texture_query.height / 2,
-                    );
-                    let new_bottom_revealed_height =
-                        (bottom_half_height as f32 * bottom_flip_progress).max(0.0) as u32;
-                    let new_bottom_src_rect = Rect::new(
-                        0,
-                        texture_query.height as i32 / 2,
-                        digit_width,
-                        new_bottom_revealed_height,
-                    );
-                    let new_bottom_dest_rect = Rect::new(
-                        digit_x,
-                        rect.y + top_half_height + gap_height,
-                        digit_width,
-                        new_bottom_revealed_height,
-                    );
-                    self.canvas.copy(
-                        &current_digit_texture,
-                        self.to_sdl_rect(&new_bottom_src_rect),
-                        self.to_sdl_rect(&new_bottom_dest_rect),
-                    )?;
+                    let color =
+                        self.settings_color_to_sdl_color(FontRole::Indicator.color(&self.settings));
+                    self.fill_rounded_rect(&Rect::new(x, y, dot, dot), color, dot as i32 / 2)?;
                 }
             }
-
-            self.canvas.set_draw_color(card_color);
-            let gap_rect = Rect::new(
-                rect.x,
-                rect.y + top_half_height,
-                rect.width,
-                gap_height as u32,
-            );
-            self.canvas.fill_rect(self.to_sdl_rect(&gap_rect))?;
-        }
-
-        Ok(())
-    }
-
-    fn render_am_pm(&mut self, rect: SdlRect, is_pm: bool) -> Result<(), String> {
-        if !self.settings.use_24hour {
-            let am_pm_text = if is_pm { "PM" } else { "AM" };
-            let am_pm_surface = self
-                .mode_font
-                .render(am_pm_text)
-                .blended(SdlColor::RGB(
-                    self.settings.font_color.r,
-                    self.settings.font_color.g,
-                    self.settings.font_color.b,
-                ))
-                .map_err(|e| e.to_string())?;
-
-            let am_pm_texture = self
-                .texture_creator
-                .as_ref()
-                .unwrap()
-                .create_texture_from_surface(&am_pm_surface)
-                .map_err(|e| e.to_string())?;
-
-            let texture_query = am_pm_texture.query();
-            let x = rect.x() + rect.width() as i32 / 2 - texture_query.width as i32 / 2;
-            let y = if is_pm {
-                rect.y() + rect.height() as i32 - texture_query.height as i32 - 10
-            } else {
-                rect.y() + 10
-            };
-
-            self.canvas.copy(
-                &am_pm_texture,
-                None,
-                self.to_sdl_rect(&Rect::new(x, y, texture_query.width, texture_query.height)),
-            )?;
+            AmPmStyle::Dot => {}
         }
         Ok(())
     }
 }
 
 impl<'a> GraphicsEngine for Sdl2GraphicsEngine<'a> {
-    fn clear(&mut self) -> Result<(), String> {
+    fn clear(&mut self) -> Result<(), FlipClockError> {
+        let alpha = if self.per_pixel_alpha {
+            (self.settings.background_opacity.clamp(0.0, 1.0) * 255.0) as u8
+        } else {
+            255
+        };
         self.canvas.set_draw_color(SdlColor::RGBA(
             self.settings.background_color.r,
             self.settings.background_color.g,
             self.settings.background_color.b,
-            255,
+            alpha,
         ));
         self.canvas.clear();
         Ok(())
     }
 
-    fn present(&mut self) -> Result<(), String> {
+    fn present(&mut self) -> Result<(), FlipClockError> {
+        if self.settings.dim_level > 0.0 {
+            let alpha = (self.settings.dim_level.clamp(0.0, 1.0) * 255.0) as u8;
+            self.canvas.set_draw_color(SdlColor::RGBA(0, 0, 0, alpha));
+            self.canvas
+                .fill_rect(None)
+                .map_err(FlipClockError::Render)?;
+        }
         self.canvas.present();
         Ok(())
     }
@@ -751,17 +1113,19 @@ impl<'a> GraphicsEngine for Sdl2GraphicsEngine<'a> {
         border_width: u32,
         radius: i32,
         filled: bool,
-    ) -> Result<(), String> {
+    ) -> Result<(), FlipClockError> {
         let sdl_color = self.to_sdl_color(color);
 
         if filled {
-            self.fill_rounded_rect(rect, sdl_color, radius)?;
+            self.fill_rounded_rect(rect, sdl_color, radius)
+                .map_err(FlipClockError::Render)?;
         }
 
         if let Some(border_color) = border_color {
             if border_width > 0 {
                 let sdl_border_color = self.to_sdl_color(border_color);
-                self.draw_rounded_rect_border(rect, sdl_border_color, radius, border_width)?;
+                self.draw_rounded_rect_border(rect, sdl_border_color, radius, border_width)
+                    .map_err(FlipClockError::Render)?;
             }
         }
 
@@ -774,136 +1138,158 @@ impl<'a> GraphicsEngine for Sdl2GraphicsEngine<'a> {
         position: TimeDigitPosition,
         rect: &Rect,
         animation: Option<AnimationState>,
-    ) -> Result<(), String> {
+    ) -> Result<(), FlipClockError> {
         // Convert rect to SDL2 rect
         let sdl_rect = sdl2::rect::Rect::new(rect.x, rect.y, rect.width, rect.height);
 
         // Use the existing render_digits implementation but adapted to the new interface
-        let past_time = animation.as_ref().and_then(|a| a.previous_value);
-        let is_animating = animation.as_ref().map(|a| a.is_animating).unwrap_or(false);
-        let progress = animation.as_ref().map(|a| a.progress).unwrap_or(0.0);
-
-        self.render_digits(value, past_time, rect, is_animating, progress)
+        self.render_digits(value, position, rect, animation.as_ref())
+            .map_err(FlipClockError::Render)
     }
 
-    fn render_am_pm_indicator(&mut self, rect: &Rect, is_pm: bool) -> Result<(), String> {
-        // Convert rect to SDL2 rect
-        let sdl_rect = sdl2::rect::Rect::new(rect.x, rect.y, rect.width, rect.height);
+    fn render_date(&mut self, rect: &Rect, text: &str) -> Result<(), FlipClockError> {
+        self.render_label_card(rect, text)
+            .map_err(FlipClockError::Render)
+    }
 
-        self.render_am_pm(sdl_rect, is_pm)
+    fn render_am_pm_indicator(
+        &mut self,
+        hour_rect: &Rect,
+        cards: &Rect,
+        is_pm: bool,
+    ) -> Result<(), FlipClockError> {
+        self.render_am_pm(hour_rect, cards, is_pm)
+            .map_err(FlipClockError::Render)
     }
 
-    fn handle_events(&mut self) -> Result<bool, String> {
-        let mut event_pump = self.sdl_context.event_pump()?;
-        let mut event_count = 0; // mouse movement seems a bit aggressive so after 5 events.
+    fn handle_events(&mut self) -> Result<Vec<Action>, FlipClockError> {
+        let window_id = self.canvas.window().id();
+        let mut events = std::mem::take(&mut self.forwarded_events);
+        if let Some(queue) = &self.events {
+            events.extend(queue.borrow_mut().take_for(window_id));
+        }
+
+        let mut actions = Vec::new();
+        let mut inputs = Vec::new();
+        // The screensaver host decides when we are done, input is its business.
+        let takes_input = self.settings.screensaver_window.is_none();
 
-        for event in event_pump.poll_iter() {
-            match event {
-                Event::Quit { .. }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Escape) | Some(Keycode::Return) | Some(Keycode::Space),
+        for event in events {
+            let input = match event {
+                Event::Quit { .. } => {
+                    actions.push(Action::Quit);
+                    continue;
+                }
+                Event::Window {
+                    win_event: WindowEvent::SizeChanged(..) | WindowEvent::DisplayChanged(..),
                     ..
-                } => return Ok(true),
-                Event::KeyDown { .. }
-                | Event::MouseButtonDown { .. }
-                | Event::MouseButtonUp { .. }
-                | Event::MouseWheel { .. } => {
-                    if self.settings.close_on_any_input {
-                        return Ok(true);
-                    }
-                    return Ok(false);
                 }
-                Event::MouseMotion { .. } => {
-                    event_count += 1;
-                    if event_count > 4 {
-                        return Ok(true);
+                | Event::Display { .. } => {
+                    self.refresh_output_size()?;
+                    continue;
+                }
+                _ if !takes_input => continue,
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    keymod,
+                    repeat: false,
+                    ..
+                } => {
+                    let combo = KeyCombo::new(&keycode.name(), Self::modifiers(keymod));
+                    match self.keybindings.action_for(&combo) {
+                        Some(action) => {
+                            actions.push(action);
+                            continue;
+                        }
+                        None => InputEvent::Key,
                     }
-                    return Ok(false);
                 }
-                _ => return Ok(false),
-            }
+                // Touches also arrive as synthesized mouse events, they are handled as fingers.
+                Event::MouseButtonDown { which, .. }
+                | Event::MouseWheel { which, .. }
+                | Event::MouseMotion { which, .. }
+                    if which == TOUCH_MOUSE_ID =>
+                {
+                    continue;
+                }
+                Event::MouseButtonDown { .. } => InputEvent::Button,
+                Event::MouseWheel { .. } => InputEvent::Wheel,
+                Event::MouseMotion { x, y, .. } => InputEvent::Motion { x, y },
+                Event::FingerDown { .. } => InputEvent::Touch,
+                _ => continue,
+            };
+            inputs.push(input);
+        }
+
+        match self
+            .input_policy
+            .respond_all(inputs, self.started.elapsed())
+        {
+            InputResponse::Exit => actions.push(Action::Quit),
+            InputResponse::Pause => actions.push(Action::TogglePause),
+            InputResponse::Ignore => {}
         }
 
-        Ok(false)
+        Ok(actions)
     }
 
     fn calculate_layout(&self) -> ClockLayout {
-        let is_horizontal = self.settings.width > self.settings.height;
-        let rect_size = if is_horizontal {
-            (self.settings.height as f32 * RECT_SIZE_SCALE) as u32 // Uses height for horizontal
-        } else {
-            (self.settings.width as f32 * RECT_SIZE_SCALE) as u32 // Uses width for vertical
-        };
-
-        let spacing = if is_horizontal {
-            (self.settings.width as f32 * 0.031) as i32 // Uses width for horizontal spacing
-        } else {
-            (self.settings.height as f32 * 0.031) as i32 // Uses height for vertical spacing
-        };
-
-        let hour_rect = if is_horizontal {
-            Rect::new(
-                ((self.settings.width as i32 - spacing - (rect_size as i32 * 2)) / 2) as i32, // Centers horizontally
-                ((self.settings.height as i32 - rect_size as i32) / 2) as i32, // Centers vertically
-                rect_size,
-                rect_size,
-            )
-        } else {
-            Rect::new(
-                ((self.settings.width as i32 - rect_size as i32) / 2) as i32, // Centers horizontally
-                ((self.settings.height as i32 - spacing - (rect_size as i32 * 2)) / 2) as i32, // Centers vertically
-                rect_size,
-                rect_size,
-            )
-        };
+        let (width, height) = self.output_size;
+        ClockLayout::calculate(width, height, &self.settings)
+    }
 
-        let minute_rect = if is_horizontal {
-            Rect::new(
-                hour_rect.x + rect_size as i32 + spacing, // Positioned to the right of hour
-                hour_rect.y,
-                rect_size,
-                rect_size,
-            )
-        } else {
-            Rect::new(
-                hour_rect.x,
-                hour_rect.y + rect_size as i32 + spacing, // Positioned below hour
-                rect_size,
-                rect_size,
-            )
-        };
+    fn get_settings(&self) -> &ClockSettings {
+        &self.settings
+    }
 
-        let seconds_rect = if self.settings.show_seconds {
-            Some(if is_horizontal {
-                Rect::new(
-                    minute_rect.x + rect_size as i32 + spacing, // Positioned to the right of minute
-                    minute_rect.y,
-                    rect_size,
-                    rect_size,
-                )
+    fn update_settings(&mut self, settings: ClockSettings) -> Result<(), FlipClockError> {
+        if settings.fullscreen != self.settings.fullscreen
+            && settings.screensaver_window.is_none()
+            && settings.multi_display != MultiDisplay::Span
+        {
+            let fullscreen_type = if settings.fullscreen {
+                FullscreenType::Desktop
             } else {
-                Rect::new(
-                    minute_rect.x,
-                    minute_rect.y + rect_size as i32 + spacing, // Positioned below minute
-                    rect_size,
-                    rect_size,
-                )
-            })
-        } else {
-            None
-        };
+                FullscreenType::Off
+            };
+            self.canvas
+                .window_mut()
+                .set_fullscreen(fullscreen_type)
+                .map_err(FlipClockError::Video)?;
+        }
+
+        if settings.transparent
+            && !self.per_pixel_alpha
+            && settings.background_opacity != self.settings.background_opacity
+        {
+            Self::set_window_opacity(self.canvas.window_mut(), settings.background_opacity);
+        }
 
-        ClockLayout {
-            hour_rect,
-            minute_rect,
-            second_rect: seconds_rect,
-            is_horizontal,
-            rect_size,
-            spacing,
+        if settings.font_changed(&self.settings) {
+            let font_files = Self::find_fonts(&settings)?;
+            self.fonts = Self::load_fonts(
+                self.ttf_context,
+                &settings,
+                &font_files,
+                self.font_rect_size,
+            )?;
+            self.font_files = font_files;
         }
+
+        self.settings = settings;
+        self.refresh_output_size()
     }
 
-    fn get_settings(&self) -> &ClockSettings {
-        &self.settings
+    fn read_pixels(&mut self) -> Result<Frame, FlipClockError> {
+        let (width, height) = self.canvas.output_size().map_err(FlipClockError::Render)?;
+        let pixels = self
+            .canvas
+            .read_pixels(None, PixelFormatEnum::RGBA32)
+            .map_err(FlipClockError::Render)?;
+        Ok(Frame {
+            width,
+            height,
+            pixels,
+        })
     }
 }
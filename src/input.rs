@@ -0,0 +1,314 @@
+use crate::config::{ClockSettings, InputResponse};
+use std::time::Duration;
+
+/// Backend independent classes of user input.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputEvent {
+    Key,
+    Button,
+    Wheel,
+    /// Absolute pointer position in window coordinates.
+    Motion {
+        x: i32,
+        y: i32,
+    },
+    Touch,
+}
+
+/// Decides what user input does to the clock: exit, pause, or nothing.
+pub struct InputPolicy {
+    motion_threshold_px: u32,
+    grace_period: Duration,
+    on_key: InputResponse,
+    on_button: InputResponse,
+    on_wheel: InputResponse,
+    on_motion: InputResponse,
+    on_touch: InputResponse,
+    motion_anchor: Option<(i32, i32)>,
+}
+
+impl InputPolicy {
+    pub fn new(settings: &ClockSettings) -> Self {
+        InputPolicy {
+            motion_threshold_px: settings.motion_threshold_px,
+            grace_period: Duration::from_millis(settings.input_grace_period_ms as u64),
+            on_key: settings.on_key,
+            on_button: settings.on_button,
+            on_wheel: settings.on_wheel,
+            on_motion: settings.on_motion,
+            on_touch: settings.on_touch,
+            motion_anchor: None,
+        }
+    }
+
+    /// Response to a single event, `since_start` being the time since the clock started.
+    pub fn respond(&mut self, event: InputEvent, since_start: Duration) -> InputResponse {
+        if since_start < self.grace_period {
+            // Motion during the grace period must not count towards the threshold either.
+            self.motion_anchor = None;
+            return InputResponse::Ignore;
+        }
+
+        match event {
+            InputEvent::Key => self.on_key,
+            InputEvent::Button => self.on_button,
+            InputEvent::Wheel => self.on_wheel,
+            InputEvent::Touch => self.on_touch,
+            InputEvent::Motion { x, y } => self.respond_to_motion(x, y),
+        }
+    }
+
+    /// Strongest response to a batch of events, exit winning over pause.
+    pub fn respond_all<I: IntoIterator<Item = InputEvent>>(
+        &mut self,
+        events: I,
+        since_start: Duration,
+    ) -> InputResponse {
+        events
+            .into_iter()
+            .map(|event| self.respond(event, since_start))
+            .max()
+            .unwrap_or(InputResponse::Ignore)
+    }
+
+    /// Motion only counts once the pointer has moved further than the
+    /// threshold away from where it was first seen, so a bumped desk or a
+    /// jittery sensor does not end the clock.
+    fn respond_to_motion(&mut self, x: i32, y: i32) -> InputResponse {
+        if self.on_motion == InputResponse::Ignore {
+            return InputResponse::Ignore;
+        }
+
+        let (anchor_x, anchor_y) = *self.motion_anchor.get_or_insert((x, y));
+        let dx = (x - anchor_x) as f64;
+        let dy = (y - anchor_y) as f64;
+        if (dx * dx + dy * dy).sqrt() <= self.motion_threshold_px as f64 {
+            return InputResponse::Ignore;
+        }
+
+        self.motion_anchor = None;
+        self.on_motion
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AFTER_GRACE: Duration = Duration::from_secs(5);
+
+    fn settings() -> ClockSettings {
+        ClockSettings {
+            motion_threshold_px: 20,
+            input_grace_period_ms: 1000,
+            on_key: InputResponse::Ignore,
+            on_button: InputResponse::Ignore,
+            on_wheel: InputResponse::Ignore,
+            on_motion: InputResponse::Exit,
+            on_touch: InputResponse::Ignore,
+            ..ClockSettings::default()
+        }
+    }
+
+    fn motion(x: i32, y: i32) -> InputEvent {
+        InputEvent::Motion { x, y }
+    }
+
+    #[test]
+    fn small_motion_stays_below_threshold() {
+        let mut policy = InputPolicy::new(&settings());
+        let events = [
+            motion(100, 100),
+            motion(105, 103),
+            motion(112, 110),
+            motion(90, 95),
+        ];
+        assert_eq!(
+            policy.respond_all(events, AFTER_GRACE),
+            InputResponse::Ignore
+        );
+    }
+
+    #[test]
+    fn many_small_motion_events_do_not_add_up() {
+        let mut policy = InputPolicy::new(&settings());
+        let jitter = (0..100).map(|i| motion(100 + i % 3, 100 - i % 2));
+        assert_eq!(
+            policy.respond_all(jitter, AFTER_GRACE),
+            InputResponse::Ignore
+        );
+    }
+
+    #[test]
+    fn motion_beyond_threshold_exits() {
+        let mut policy = InputPolicy::new(&settings());
+        let events = [motion(100, 100), motion(110, 100), motion(121, 100)];
+        assert_eq!(policy.respond_all(events, AFTER_GRACE), InputResponse::Exit);
+    }
+
+    #[test]
+    fn threshold_is_a_distance_not_per_axis() {
+        let mut policy = InputPolicy::new(&settings());
+        // 15 px on both axes is ~21 px away.
+        let events = [motion(0, 0), motion(15, 15)];
+        assert_eq!(policy.respond_all(events, AFTER_GRACE), InputResponse::Exit);
+    }
+
+    #[test]
+    fn motion_spread_over_polls_accumulates() {
+        let mut policy = InputPolicy::new(&settings());
+        assert_eq!(
+            policy.respond(motion(0, 0), AFTER_GRACE),
+            InputResponse::Ignore
+        );
+        assert_eq!(
+            policy.respond(motion(0, 15), AFTER_GRACE),
+            InputResponse::Ignore
+        );
+        assert_eq!(
+            policy.respond(motion(0, 25), AFTER_GRACE),
+            InputResponse::Exit
+        );
+    }
+
+    #[test]
+    fn input_during_grace_period_is_ignored() {
+        let settings = ClockSettings {
+            on_key: InputResponse::Exit,
+            ..settings()
+        };
+        let mut policy = InputPolicy::new(&settings);
+        let early = Duration::from_millis(500);
+        let events = [InputEvent::Key, motion(0, 0), motion(500, 500)];
+        assert_eq!(policy.respond_all(events, early), InputResponse::Ignore);
+    }
+
+    #[test]
+    fn motion_during_grace_period_does_not_set_the_anchor() {
+        let mut policy = InputPolicy::new(&settings());
+        // Window appears under the pointer while it is still settling.
+        policy.respond(motion(0, 0), Duration::from_millis(100));
+        assert_eq!(
+            policy.respond(motion(300, 300), AFTER_GRACE),
+            InputResponse::Ignore
+        );
+        assert_eq!(
+            policy.respond(motion(305, 305), AFTER_GRACE),
+            InputResponse::Ignore
+        );
+    }
+
+    #[test]
+    fn each_event_class_uses_its_own_response() {
+        let settings = ClockSettings {
+            on_key: InputResponse::Exit,
+            on_button: InputResponse::Pause,
+            on_wheel: InputResponse::Ignore,
+            on_touch: InputResponse::Pause,
+            ..settings()
+        };
+        let mut policy = InputPolicy::new(&settings);
+        assert_eq!(
+            policy.respond(InputEvent::Key, AFTER_GRACE),
+            InputResponse::Exit
+        );
+        assert_eq!(
+            policy.respond(InputEvent::Button, AFTER_GRACE),
+            InputResponse::Pause
+        );
+        assert_eq!(
+            policy.respond(InputEvent::Wheel, AFTER_GRACE),
+            InputResponse::Ignore
+        );
+        assert_eq!(
+            policy.respond(InputEvent::Touch, AFTER_GRACE),
+            InputResponse::Pause
+        );
+    }
+
+    #[test]
+    fn ignored_motion_never_triggers() {
+        let settings = ClockSettings {
+            on_motion: InputResponse::Ignore,
+            ..settings()
+        };
+        let mut policy = InputPolicy::new(&settings);
+        let events = [motion(0, 0), motion(1000, 1000)];
+        assert_eq!(
+            policy.respond_all(events, AFTER_GRACE),
+            InputResponse::Ignore
+        );
+    }
+
+    #[test]
+    fn exit_wins_over_pause_in_one_batch() {
+        let settings = ClockSettings {
+            on_button: InputResponse::Pause,
+            on_key: InputResponse::Exit,
+            ..settings()
+        };
+        let mut policy = InputPolicy::new(&settings);
+        let events = [InputEvent::Button, InputEvent::Key, InputEvent::Button];
+        assert_eq!(policy.respond_all(events, AFTER_GRACE), InputResponse::Exit);
+    }
+
+    #[test]
+    fn pausing_motion_needs_a_fresh_threshold_to_trigger_again() {
+        let settings = ClockSettings {
+            on_motion: InputResponse::Pause,
+            ..settings()
+        };
+        let mut policy = InputPolicy::new(&settings);
+        assert_eq!(
+            policy.respond(motion(0, 0), AFTER_GRACE),
+            InputResponse::Ignore
+        );
+        assert_eq!(
+            policy.respond(motion(30, 0), AFTER_GRACE),
+            InputResponse::Pause
+        );
+        assert_eq!(
+            policy.respond(motion(40, 0), AFTER_GRACE),
+            InputResponse::Ignore
+        );
+        assert_eq!(
+            policy.respond(motion(60, 0), AFTER_GRACE),
+            InputResponse::Ignore
+        );
+        assert_eq!(
+            policy.respond(motion(61, 0), AFTER_GRACE),
+            InputResponse::Pause
+        );
+    }
+
+    #[test]
+    fn empty_poll_is_ignored() {
+        let mut policy = InputPolicy::new(&settings());
+        assert_eq!(policy.respond_all([], AFTER_GRACE), InputResponse::Ignore);
+    }
+
+    #[test]
+    fn close_on_any_input_upgrades_every_class_but_motion() {
+        let mut config = crate::config::Config::default();
+        config.window.close_on_any_input = true;
+        config.input.on_motion = InputResponse::Pause;
+        let settings = config.to_clock_settings();
+        let mut policy = InputPolicy::new(&settings);
+        for event in [
+            InputEvent::Key,
+            InputEvent::Button,
+            InputEvent::Wheel,
+            InputEvent::Touch,
+        ] {
+            assert_eq!(policy.respond(event, AFTER_GRACE), InputResponse::Exit);
+        }
+        assert_eq!(
+            policy.respond(motion(0, 0), AFTER_GRACE),
+            InputResponse::Ignore
+        );
+        assert_eq!(
+            policy.respond(motion(0, 100), AFTER_GRACE),
+            InputResponse::Pause
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use crate::config::Action;
+use crate::error::FlipClockError;
+use std::path::PathBuf;
+
+/// Where a running clock listens for actions from other processes: the
+/// user's runtime directory, which other users cannot get into. A shared
+/// directory such as `/tmp` would let anyone control the clock.
+pub fn socket_path() -> Result<PathBuf, FlipClockError> {
+    dirs::runtime_dir()
+        // macOS has no runtime directory, but every user a temporary one of their own.
+        .or_else(|| cfg!(target_os = "macos").then(std::env::temp_dir))
+        .map(|dir| dir.join("flipclock.sock"))
+        .ok_or_else(|| {
+            FlipClockError::io(
+                "XDG_RUNTIME_DIR is not set, there is no private place for the control socket",
+                std::io::ErrorKind::NotFound.into(),
+            )
+        })
+}
+
+/// The longest `ControlSocket::poll` waits for clients, all of them together.
+#[cfg(unix)]
+const POLL_BUDGET: std::time::Duration = std::time::Duration::from_millis(10);
+
+/// A Unix socket that accepts action names, one per line, using the same
+/// names as `[keybindings]`, e.g. `echo toggle_seconds | nc -U $XDG_RUNTIME_DIR/flipclock.sock`.
+#[cfg(unix)]
+pub struct ControlSocket {
+    listener: std::os::unix::net::UnixListener,
+    path: PathBuf,
+}
+
+#[cfg(unix)]
+impl ControlSocket {
+    pub fn bind() -> Result<Self, FlipClockError> {
+        Self::bind_at(socket_path()?)
+    }
+
+    /// Listens on `path` instead of the usual socket.
+    pub fn bind_at(path: PathBuf) -> Result<Self, FlipClockError> {
+        use std::os::unix::net::{UnixListener, UnixStream};
+
+        let listener = match UnixListener::bind(&path) {
+            Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => {
+                if UnixStream::connect(&path).is_ok() {
+                    return Err(FlipClockError::io(
+                        format!("Another clock is listening on {}", path.display()),
+                        e,
+                    ));
+                }
+                // Left behind by a clock that did not shut down cleanly.
+                std::fs::remove_file(&path).and_then(|_| UnixListener::bind(&path))
+            }
+            bound => bound,
+        }
+        .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+        .map_err(|e| FlipClockError::io(format!("Failed to listen on {}", path.display()), e))?;
+
+        Ok(ControlSocket { listener, path })
+    }
+
+    /// Actions received since the last call. Never blocks the render loop
+    /// for longer than `POLL_BUDGET`, however slowly clients write, and
+    /// leaves connections still waiting to be accepted for the next call.
+    pub fn poll(&mut self) -> Vec<Action> {
+        use std::io::{BufRead, BufReader, Write};
+        use std::time::Instant;
+
+        let deadline = Instant::now() + POLL_BUDGET;
+        // A zero timeout would make reads block forever.
+        let time_left = || {
+            deadline
+                .checked_duration_since(Instant::now())
+                .filter(|left| !left.is_zero())
+        };
+        let mut actions = Vec::new();
+        while time_left().is_some()
+            && let Ok((stream, _)) = self.listener.accept()
+        {
+            if stream.set_nonblocking(false).is_err() {
+                continue;
+            }
+            let mut writer = match stream.try_clone() {
+                Ok(writer) => writer,
+                Err(_) => continue,
+            };
+            let mut reader = BufReader::new(stream);
+            let mut line = String::new();
+            while let Some(left) = time_left()
+                && reader.get_ref().set_read_timeout(Some(left)).is_ok()
+                && reader.read_line(&mut line).is_ok_and(|read| read > 0)
+            {
+                let command = std::mem::take(&mut line);
+                if command.trim().is_empty() {
+                    continue;
+                }
+                let reply = match command.trim().parse::<Action>() {
+                    Ok(action) => {
+                        actions.push(action);
+                        "ok".to_string()
+                    }
+                    Err(e) => format!("error: {}", e),
+                };
+                let _ = writeln!(writer, "{}", reply);
+            }
+        }
+        actions
+    }
+}
+
+#[cfg(unix)]
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Sends actions to a running clock and returns its replies.
+#[cfg(unix)]
+pub fn send(actions: &[String]) -> Result<String, FlipClockError> {
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+
+    let path = socket_path()?;
+    let mut stream = UnixStream::connect(&path).map_err(|e| {
+        FlipClockError::io(format!("No clock is listening on {}", path.display()), e)
+    })?;
+    let mut reply = String::new();
+    actions
+        .iter()
+        .try_for_each(|action| writeln!(stream, "{}", action))
+        .and_then(|_| stream.shutdown(std::net::Shutdown::Write))
+        .and_then(|_| stream.read_to_string(&mut reply))
+        .map_err(|e| FlipClockError::io("Failed to talk to the running clock", e))?;
+    Ok(reply)
+}
+
+#[cfg(not(unix))]
+pub struct ControlSocket;
+
+#[cfg(not(unix))]
+impl ControlSocket {
+    pub fn bind() -> Result<Self, FlipClockError> {
+        Err(unsupported())
+    }
+
+    pub fn poll(&mut self) -> Vec<Action> {
+        Vec::new()
+    }
+}
+
+#[cfg(not(unix))]
+pub fn send(_actions: &[String]) -> Result<String, FlipClockError> {
+    Err(unsupported())
+}
+
+#[cfg(not(unix))]
+fn unsupported() -> FlipClockError {
+    FlipClockError::io(
+        "Remote control is only supported on Unix",
+        std::io::ErrorKind::Unsupported.into(),
+    )
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+    use std::time::Instant;
+
+    #[test]
+    fn client_keeping_the_connection_open_does_not_stall_poll() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("flipclock.sock");
+        let mut socket = ControlSocket::bind_at(path.clone()).unwrap();
+
+        // Waits for the reply without ever closing its end.
+        let mut client = UnixStream::connect(&path).unwrap();
+        writeln!(client, "toggle_seconds").unwrap();
+
+        let started = Instant::now();
+        assert_eq!(socket.poll(), vec![Action::ToggleSeconds]);
+        assert!(started.elapsed() < POLL_BUDGET * 5);
+
+        let mut reply = String::new();
+        BufReader::new(&client).read_line(&mut reply).unwrap();
+        assert_eq!(reply, "ok\n");
+    }
+}
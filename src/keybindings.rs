@@ -0,0 +1,203 @@
+use crate::config::{Action, ConfigError};
+
+/// Modifier keys held down together with a key.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub logo: bool,
+}
+
+/// A key with its modifiers, written as e.g. `"Ctrl+Shift+Q"` or `"F11"` in
+/// the config. Key names follow SDL's (`Escape`, `Return`, `Space`, `Left`, ...)
+/// and are matched case-insensitively.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyCombo {
+    key: String,
+    modifiers: Modifiers,
+}
+
+impl KeyCombo {
+    pub fn new(key: &str, modifiers: Modifiers) -> Self {
+        KeyCombo {
+            key: key.to_lowercase(),
+            modifiers,
+        }
+    }
+
+    pub fn parse(combo: &str) -> Result<Self, ConfigError> {
+        let trimmed = combo.trim();
+        // A "+" on its own or after another one is the plus key itself: "+", "Ctrl++".
+        let (modifiers_part, key) = match trimmed.strip_suffix('+').map(str::trim_end) {
+            Some("") => (None, "+"),
+            Some(rest) if rest.ends_with('+') => (rest.strip_suffix('+'), "+"),
+            _ => match trimmed.rsplit_once('+') {
+                Some((modifiers, key)) => (Some(modifiers), key.trim()),
+                None => (None, trimmed),
+            },
+        };
+        if key.is_empty() {
+            return Err(ConfigError::InvalidKeybinding(combo.to_string()));
+        }
+        let parts = modifiers_part.into_iter().flat_map(|part| part.split('+'));
+
+        let mut modifiers = Modifiers::default();
+        for modifier in parts {
+            match modifier.trim().to_lowercase().as_str() {
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "alt" => modifiers.alt = true,
+                "shift" => modifiers.shift = true,
+                "super" | "logo" | "meta" | "gui" => modifiers.logo = true,
+                _ => {
+                    return Err(ConfigError::InvalidKeybinding(format!(
+                        "{} (unknown modifier {})",
+                        combo,
+                        modifier.trim()
+                    )));
+                }
+            }
+        }
+
+        Ok(KeyCombo::new(key, modifiers))
+    }
+}
+
+pub struct Keybindings {
+    bindings: Vec<(KeyCombo, Action)>,
+}
+
+impl Keybindings {
+    /// Later bindings for the same combo replace earlier ones, and binding a
+    /// combo to `Action::Unbound` removes it.
+    pub fn parse(bindings: &[(String, Action)]) -> Result<Self, ConfigError> {
+        let mut parsed: Vec<(KeyCombo, Action)> = Vec::new();
+        for (combo, action) in bindings {
+            let combo = KeyCombo::parse(combo)?;
+            parsed.retain(|(existing, _)| *existing != combo);
+            if *action != Action::Unbound {
+                parsed.push((combo, *action));
+            }
+        }
+        Ok(Keybindings { bindings: parsed })
+    }
+
+    pub fn action_for(&self, combo: &KeyCombo) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| bound == combo)
+            .map(|(_, action)| *action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    const CTRL: Modifiers = Modifiers {
+        ctrl: true,
+        alt: false,
+        shift: false,
+        logo: false,
+    };
+
+    fn bindings(config: &str) -> Keybindings {
+        let config: Config = toml::from_str(config).unwrap();
+        Keybindings::parse(&config.to_clock_settings().keybindings).unwrap()
+    }
+
+    #[test]
+    fn plain_key_is_case_insensitive() {
+        assert_eq!(
+            KeyCombo::parse("f11").unwrap(),
+            KeyCombo::new("F11", Modifiers::default())
+        );
+    }
+
+    #[test]
+    fn modifiers_and_their_aliases() {
+        let all = Modifiers {
+            ctrl: true,
+            alt: true,
+            shift: true,
+            logo: true,
+        };
+        assert_eq!(
+            KeyCombo::parse("Ctrl+Alt+Shift+Super+Q").unwrap(),
+            KeyCombo::new("q", all)
+        );
+        assert_eq!(
+            KeyCombo::parse("control + meta + shift + alt + q").unwrap(),
+            KeyCombo::new("q", all)
+        );
+        for logo in ["Logo+Q", "GUI+Q", "Meta+Q"] {
+            assert!(KeyCombo::parse(logo).unwrap().modifiers.logo);
+        }
+    }
+
+    #[test]
+    fn unknown_or_empty_modifiers_are_rejected() {
+        for combo in ["Hyper+Q", "+Q", "Ctrl++Q", "Ctrl+", "", "  "] {
+            assert!(
+                matches!(
+                    KeyCombo::parse(combo),
+                    Err(ConfigError::InvalidKeybinding(_))
+                ),
+                "{combo:?} parsed"
+            );
+        }
+    }
+
+    #[test]
+    fn plus_key() {
+        assert_eq!(
+            KeyCombo::parse("+").unwrap(),
+            KeyCombo::new("+", Modifiers::default())
+        );
+        assert_eq!(KeyCombo::parse("Ctrl++").unwrap(), KeyCombo::new("+", CTRL));
+        assert_eq!(
+            KeyCombo::parse("Ctrl + +").unwrap(),
+            KeyCombo::new("+", CTRL)
+        );
+    }
+
+    #[test]
+    fn later_bindings_override_earlier_ones() {
+        let bindings = Keybindings::parse(&[
+            ("Ctrl+Q".to_string(), Action::Quit),
+            ("ctrl+q".to_string(), Action::TogglePause),
+        ])
+        .unwrap();
+        assert_eq!(
+            bindings.action_for(&KeyCombo::new("Q", CTRL)),
+            Some(Action::TogglePause)
+        );
+    }
+
+    #[test]
+    fn config_overrides_defaults() {
+        let bindings = bindings("[keybindings]\nS = \"next_theme\"\n");
+        assert_eq!(
+            bindings.action_for(&KeyCombo::new("S", Modifiers::default())),
+            Some(Action::NextTheme)
+        );
+        assert_eq!(
+            bindings.action_for(&KeyCombo::new("H", Modifiers::default())),
+            Some(Action::Toggle24Hour)
+        );
+    }
+
+    #[test]
+    fn none_unbinds_a_default() {
+        let bindings = bindings("[keybindings]\nSpace = \"none\"\n");
+        assert_eq!(
+            bindings.action_for(&KeyCombo::new("Space", Modifiers::default())),
+            None
+        );
+        assert_eq!(
+            bindings.action_for(&KeyCombo::new("Escape", Modifiers::default())),
+            Some(Action::Quit)
+        );
+    }
+}
@@ -0,0 +1,36 @@
+//! A flip clock that draws through a [`GraphicsEngine`]: SDL2 windows with
+//! the `sdl` feature, a terminal, the Linux framebuffer, images and SVG.
+//!
+//! To put the clock into an SDL application of your own, hand its canvas to
+//! [`Sdl2GraphicsEngine::with_canvas`] and call [`FlipClock::tick`] from your
+//! event loop, see `examples/embedded.rs`.
+
+pub mod clock;
+pub mod config;
+pub mod error;
+pub mod export;
+pub mod font;
+#[cfg(target_os = "linux")]
+pub mod framebuffer;
+pub mod graphics_engine;
+#[cfg(feature = "sdl")]
+pub mod graphics_engine_impl;
+pub mod input;
+pub mod ipc;
+pub mod keybindings;
+pub mod locale;
+pub mod raster;
+pub mod recording;
+pub mod software_engine;
+pub mod svg;
+pub mod tui;
+pub mod x11;
+
+pub use clock::{FlipClock, TimeSource};
+pub use config::{ClockSettings, Config};
+pub use error::FlipClockError;
+pub use graphics_engine::{ClockLayout, GraphicsEngine};
+#[cfg(feature = "sdl")]
+pub use graphics_engine_impl::Sdl2GraphicsEngine;
+#[cfg(feature = "sdl")]
+pub use sdl2;
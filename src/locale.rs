@@ -0,0 +1,181 @@
+use crate::config::{ClockSettings, Numerals};
+use crate::graphics_engine::TimeDigitPosition;
+
+/// AM and PM in the languages that don't write them like English, by
+/// ISO 639-1 code.
+const MERIDIEMS: &[(&str, &str, &str)] = &[
+    ("ar", "ص", "م"),
+    ("cs", "dop.", "odp."),
+    ("de", "vorm.", "nachm."),
+    ("el", "π.μ.", "μ.μ."),
+    ("es", "a. m.", "p. m."),
+    ("fa", "ق.ظ.", "ب.ظ."),
+    ("fi", "ap.", "ip."),
+    ("he", "לפנה״צ", "אחה״צ"),
+    ("hu", "de.", "du."),
+    ("ja", "午前", "午後"),
+    ("ko", "오전", "오후"),
+    ("nb", "a.m.", "p.m."),
+    ("nl", "a.m.", "p.m."),
+    ("pt", "a.m.", "p.m."),
+    ("ru", "ДП", "ПП"),
+    ("sv", "fm", "em"),
+    ("th", "ก่อนเที่ยง", "หลังเที่ยง"),
+    ("tr", "ÖÖ", "ÖS"),
+    ("uk", "дп", "пп"),
+    ("vi", "SA", "CH"),
+    ("zh", "上午", "下午"),
+];
+
+/// The locale times are formatted for, from the environment like `date` does.
+pub fn time_locale() -> Option<String> {
+    ["LC_ALL", "LC_TIME", "LANG"]
+        .into_iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+/// AM and PM as written in `locale`, e.g. `de_DE.UTF-8`. English for
+/// unknown languages and the `C` locale.
+pub fn meridiem(locale: Option<&str>) -> [String; 2] {
+    let language = locale
+        .and_then(|locale| locale.split(['_', '-', '.', '@']).next())
+        .unwrap_or_default()
+        .to_lowercase();
+    let (am, pm) = MERIDIEMS
+        .iter()
+        .find(|(code, _, _)| *code == language)
+        .map_or(("AM", "PM"), |(_, am, pm)| (am, pm));
+    [am.to_string(), pm.to_string()]
+}
+
+impl Numerals {
+    /// The ten digits of the system, zero first. Roman numerals only replace
+    /// the hours, the other cards keep Western digits.
+    pub fn digits(self) -> &'static str {
+        match self {
+            Numerals::Western | Numerals::Roman => "0123456789",
+            Numerals::ArabicIndic => "٠١٢٣٤٥٦٧٨٩",
+            Numerals::Devanagari => "०१२३४५६७८९",
+            Numerals::FullWidth => "０１２３４５６７８９",
+        }
+    }
+
+    /// Every character a card can show.
+    pub fn characters(self) -> String {
+        match self {
+            Numerals::Roman => format!("{}NIVXLC", self.digits()),
+            _ => self.digits().to_string(),
+        }
+    }
+}
+
+/// The text of a card showing `value`.
+pub fn format_value(value: u32, position: TimeDigitPosition, settings: &ClockSettings) -> String {
+    if settings.numerals == Numerals::Roman && position == TimeDigitPosition::Hour {
+        return roman(value);
+    }
+    let western = if settings.show_leading_zero {
+        format!("{:02}", value)
+    } else {
+        format!("{}", value)
+    };
+    let digits: Vec<char> = settings.numerals.digits().chars().collect();
+    western
+        .chars()
+        .map(|c| c.to_digit(10).map_or(c, |digit| digits[digit as usize]))
+        .collect()
+}
+
+/// `value` in Roman numerals, with `N` (nulla) for the zero they lack.
+fn roman(mut value: u32) -> String {
+    if value == 0 {
+        return "N".to_string();
+    }
+    const SYMBOLS: [(u32, &str); 9] = [
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let mut numeral = String::new();
+    for (amount, symbol) in SYMBOLS {
+        while value >= amount {
+            numeral.push_str(symbol);
+            value -= amount;
+        }
+    }
+    numeral
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(numerals: Numerals, show_leading_zero: bool) -> ClockSettings {
+        ClockSettings {
+            numerals,
+            show_leading_zero,
+            ..ClockSettings::default()
+        }
+    }
+
+    #[test]
+    fn roman_numerals_subtract_and_start_at_nulla() {
+        assert_eq!(roman(0), "N");
+        assert_eq!(roman(4), "IV");
+        assert_eq!(roman(9), "IX");
+        assert_eq!(roman(12), "XII");
+        assert_eq!(roman(40), "XL");
+        assert_eq!(roman(90), "XC");
+        // Timer hours go up to 99.
+        assert_eq!(roman(99), "XCIX");
+    }
+
+    #[test]
+    fn leading_zero_is_mapped_like_any_other_digit() {
+        let minute = TimeDigitPosition::Minute;
+        assert_eq!(
+            format_value(7, minute, &settings(Numerals::Western, true)),
+            "07"
+        );
+        assert_eq!(
+            format_value(7, minute, &settings(Numerals::Western, false)),
+            "7"
+        );
+        assert_eq!(
+            format_value(7, minute, &settings(Numerals::ArabicIndic, true)),
+            "٠٧"
+        );
+        assert_eq!(
+            format_value(45, minute, &settings(Numerals::Devanagari, true)),
+            "४५"
+        );
+        assert_eq!(
+            format_value(3, minute, &settings(Numerals::FullWidth, false)),
+            "３"
+        );
+    }
+
+    #[test]
+    fn roman_numerals_only_replace_the_hours() {
+        let roman = settings(Numerals::Roman, true);
+        assert_eq!(format_value(9, TimeDigitPosition::Hour, &roman), "IX");
+        assert_eq!(format_value(0, TimeDigitPosition::Hour, &roman), "N");
+        assert_eq!(format_value(9, TimeDigitPosition::Minute, &roman), "09");
+        assert_eq!(format_value(9, TimeDigitPosition::Second, &roman), "09");
+    }
+
+    #[test]
+    fn meridiem_follows_the_language_of_the_locale() {
+        assert_eq!(meridiem(Some("de_DE.UTF-8")), ["vorm.", "nachm."]);
+        assert_eq!(meridiem(Some("ja_JP")), ["午前", "午後"]);
+        assert_eq!(meridiem(Some("C")), ["AM", "PM"]);
+        assert_eq!(meridiem(None), ["AM", "PM"]);
+    }
+}
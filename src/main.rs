@@ -1,18 +1,252 @@
-mod clock;
-mod config;
-mod graphics_engine;
-mod graphics_engine_impl;
-use clock::FlipClock;
-use config::Config;
-use graphics_engine_impl::Sdl2GraphicsEngine;
-
-fn main() -> Result<(), String> {
-    let ttf_context = sdl2::ttf::init().map_err(|e| e.to_string())?;
-
-    let config = Config::load().map_err(|e| e.to_string())?;
+mod cli;
+mod screensaver;
+use cli::{Backend, CliOptions};
+#[cfg(feature = "sdl")]
+use flipclock::Sdl2GraphicsEngine;
+#[cfg(target_os = "linux")]
+use flipclock::framebuffer;
+use flipclock::ipc::ControlSocket;
+use flipclock::software_engine::{Offscreen, SoftwareEngine};
+use flipclock::svg::SvgEngine;
+use flipclock::{Config, FlipClock, FlipClockError, GraphicsEngine, TimeSource, ipc, tui};
+use std::error::Error;
+use std::process::ExitCode;
+use std::time::Duration;
+
+/// Length of a recording when `--duration` is not given.
+const DEFAULT_RECORDING_DURATION: Duration = Duration::from_secs(10);
+
+fn main() -> ExitCode {
+    match try_main() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", report(&e));
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+/// `e` followed by everything that caused it.
+fn report(e: &FlipClockError) -> String {
+    let mut message = e.to_string();
+    let mut source = e.source();
+    while let Some(cause) = source {
+        message.push_str(&format!(": {}", cause));
+        source = cause.source();
+    }
+    message
+}
+
+fn try_main() -> Result<(), FlipClockError> {
+    let options = CliOptions::parse(std::env::args().skip(1)).map_err(FlipClockError::Usage)?;
+    if options.print_xscreensaver_config {
+        print!("{}", screensaver::xscreensaver_config());
+        return Ok(());
+    }
+    if options.print_desktop_entry {
+        print!("{}", screensaver::desktop_entry());
+        return Ok(());
+    }
+    if !options.send.is_empty() {
+        print!("{}", ipc::send(&options.send)?);
+        return Ok(());
+    }
+
+    let config = Config::load()?;
     let settings = config.to_clock_settings();
-    let engine = Sdl2GraphicsEngine::new(&ttf_context, &settings)?;
 
-    let mut clock = FlipClock::new(engine, &settings);
-    clock.run()
+    // Vector images are written straight from the layout, whatever the backend.
+    if let Some(path) = options.image_path()
+        && path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("svg"))
+    {
+        let (width, height) = options.size.unwrap_or((settings.width, settings.height));
+        let engine = SvgEngine::new(width, height, &settings, options.outline_text)?;
+        return run(vec![engine], &options);
+    }
+
+    // A wallpaper does not need a display, nor a window in the way.
+    let backend = if options.wallpaper.is_some() {
+        Backend::Headless
+    } else {
+        options.backend
+    };
+    match backend {
+        #[cfg(feature = "sdl")]
+        Backend::Sdl => {
+            let mut settings = settings;
+            let ttf_context =
+                flipclock::sdl2::ttf::init().map_err(|e| FlipClockError::Video(e.to_string()))?;
+            if let Some(window_id) = screensaver::target_window(&options)? {
+                settings.screensaver_window = Some(window_id);
+                settings.fullscreen = false;
+            }
+            let engines = Sdl2GraphicsEngine::open_all(&ttf_context, &settings)?;
+            run(engines, &options)
+        }
+        Backend::Tui => {
+            let engine = SoftwareEngine::new(tui::Terminal::open()?, &settings)?;
+            run(vec![engine], &options)
+        }
+        #[cfg(target_os = "linux")]
+        Backend::Framebuffer => {
+            let device = std::env::var("FRAMEBUFFER").unwrap_or_else(|_| "/dev/fb0".to_string());
+            let engine = SoftwareEngine::new(framebuffer::Framebuffer::open(&device)?, &settings)?;
+            run(vec![engine], &options)
+        }
+        Backend::Headless => {
+            let (width, height) = options.size.unwrap_or((settings.width, settings.height));
+            let surface = Offscreen::new(width, height);
+            let engine = SoftwareEngine::new(surface, &settings)?;
+            run(vec![engine], &options)
+        }
+        #[cfg(not(feature = "sdl"))]
+        Backend::Sdl => Err(FlipClockError::Usage(
+            "Built without SDL, pick another --backend".to_string(),
+        )),
+        #[cfg(not(target_os = "linux"))]
+        Backend::Framebuffer => Err(FlipClockError::Usage(
+            "The framebuffer backend is only supported on Linux".to_string(),
+        )),
+    }
+}
+
+/// Runs a clock for every engine until one of them quits, or saves a
+/// screenshot or a recording of the first one.
+fn run<E: GraphicsEngine>(engines: Vec<E>, options: &CliOptions) -> Result<(), FlipClockError> {
+    let time_source = options.at.map_or(TimeSource::System, TimeSource::Fixed);
+    let mut clocks: Vec<_> = engines
+        .into_iter()
+        .map(|engine| {
+            let settings = engine.get_settings().clone();
+            FlipClock::new(engine, &settings).with_time_source(time_source)
+        })
+        .collect();
+
+    if let Some(path) = options.image_path() {
+        return clocks[0].save_screenshot(path);
+    }
+    if let Some(path) = &options.record {
+        let duration = options.duration.unwrap_or(DEFAULT_RECORDING_DURATION);
+        return clocks[0].record(path, options.from, duration);
+    }
+
+    match ControlSocket::bind() {
+        Ok(socket) => listen_for_actions(&mut clocks, socket),
+        Err(e) => eprintln!("Remote control disabled: {}", e),
+    }
+    FlipClock::run_all(&mut clocks)
+}
+
+/// Hands `socket` to the first clock, `run_all` passes what it receives on to all of them.
+fn listen_for_actions<E: GraphicsEngine>(clocks: &mut [FlipClock<E>], socket: ControlSocket) {
+    if let Some(clock) = clocks.first_mut() {
+        clock.set_control_socket(socket);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use flipclock::config::{Action, ClockSettings};
+    use flipclock::graphics_engine::{AnimationState, ClockLayout, Color, Rect, TimeDigitPosition};
+    use std::io::Write;
+    use std::os::unix::net::UnixStream;
+
+    /// Draws nothing, and quits by itself after a while so a test cannot hang.
+    struct NullEngine {
+        settings: ClockSettings,
+        ticks: u32,
+    }
+
+    const GIVE_UP_TICKS: u32 = 100;
+
+    impl GraphicsEngine for NullEngine {
+        fn clear(&mut self) -> Result<(), FlipClockError> {
+            Ok(())
+        }
+
+        fn present(&mut self) -> Result<(), FlipClockError> {
+            Ok(())
+        }
+
+        fn draw_rect(
+            &mut self,
+            _rect: &Rect,
+            _color: Color,
+            _border_color: Option<Color>,
+            _border_width: u32,
+            _radius: i32,
+            _filled: bool,
+        ) -> Result<(), FlipClockError> {
+            Ok(())
+        }
+
+        fn render_digit(
+            &mut self,
+            _value: u32,
+            _position: TimeDigitPosition,
+            _rect: &Rect,
+            _animation: Option<AnimationState>,
+        ) -> Result<(), FlipClockError> {
+            Ok(())
+        }
+
+        fn render_date(&mut self, _rect: &Rect, _text: &str) -> Result<(), FlipClockError> {
+            Ok(())
+        }
+
+        fn render_am_pm_indicator(
+            &mut self,
+            _hour_rect: &Rect,
+            _cards: &Rect,
+            _is_pm: bool,
+        ) -> Result<(), FlipClockError> {
+            Ok(())
+        }
+
+        fn handle_events(&mut self) -> Result<Vec<Action>, FlipClockError> {
+            self.ticks += 1;
+            Ok(if self.ticks >= GIVE_UP_TICKS {
+                vec![Action::Quit]
+            } else {
+                Vec::new()
+            })
+        }
+
+        fn calculate_layout(&self) -> ClockLayout {
+            ClockLayout::calculate(480, 240, &self.settings)
+        }
+
+        fn get_settings(&self) -> &ClockSettings {
+            &self.settings
+        }
+
+        fn update_settings(&mut self, settings: ClockSettings) -> Result<(), FlipClockError> {
+            self.settings = settings;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn single_clock_gets_the_control_socket() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("flipclock.sock");
+        let socket = ControlSocket::bind_at(path.clone()).unwrap();
+        let settings = ClockSettings::default();
+        let engine = NullEngine {
+            settings: settings.clone(),
+            ticks: 0,
+        };
+        let mut clocks = vec![FlipClock::new(engine, &settings)];
+
+        listen_for_actions(&mut clocks, socket);
+
+        let mut client = UnixStream::connect(&path).unwrap();
+        writeln!(client, "quit").unwrap();
+        client.shutdown(std::net::Shutdown::Write).unwrap();
+        FlipClock::run_all(&mut clocks).unwrap();
+        assert!(clocks[0].engine().ticks < GIVE_UP_TICKS);
+    }
 }
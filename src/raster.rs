@@ -0,0 +1,413 @@
+use crate::config::ClockSettings;
+use crate::error::FlipClockError;
+use crate::graphics_engine::{Color, FontRole, Rect};
+use ab_glyph::{Font, FontVec, Glyph, OutlineCurve, PxScale, ScaleFont, point};
+use std::collections::HashMap;
+
+/// An RGBA image drawn in software, for backends without a GPU renderer.
+/// Drawing blends with what is already there, like SDL's blend mode.
+#[derive(Clone)]
+pub struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<Color>,
+    clip: Option<Rect>,
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32) -> Self {
+        Canvas {
+            width,
+            height,
+            pixels: vec![Color::rgba(0, 0, 0, 0); width as usize * height as usize],
+            clip: None,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        self.pixels[y as usize * self.width as usize + x as usize]
+    }
+
+    /// Every pixel as 8 bit RGBA, row by row.
+    pub fn to_rgba(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|color| [color.r, color.g, color.b, color.a])
+            .collect()
+    }
+
+    /// Limits drawing to `clip`, or to the whole canvas again for `None`.
+    pub fn set_clip(&mut self, clip: Option<Rect>) {
+        self.clip = clip;
+    }
+
+    /// Replaces every pixel, alpha included, ignoring the clip.
+    pub fn fill(&mut self, color: Color) {
+        self.pixels.fill(color);
+    }
+
+    pub fn fill_rect(&mut self, rect: &Rect, color: Color) {
+        self.fill_rounded_rect(rect, 0, color);
+    }
+
+    pub fn fill_rounded_rect(&mut self, rect: &Rect, radius: u32, color: Color) {
+        let Some((x0, y0, x1, y1)) = self.visible_area(rect) else {
+            return;
+        };
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let coverage = rounded_rect_coverage(rect, radius, x, y);
+                if coverage > 0.0 {
+                    self.blend(x, y, color, coverage);
+                }
+            }
+        }
+    }
+
+    /// Outline of a rounded rect, `thickness` pixels wide on the inside of `rect`.
+    pub fn stroke_rounded_rect(&mut self, rect: &Rect, radius: u32, thickness: u32, color: Color) {
+        if thickness == 0 {
+            return;
+        }
+        let Some((x0, y0, x1, y1)) = self.visible_area(rect) else {
+            return;
+        };
+        let inner = Rect::new(
+            rect.x + thickness as i32,
+            rect.y + thickness as i32,
+            rect.width.saturating_sub(2 * thickness),
+            rect.height.saturating_sub(2 * thickness),
+        );
+        let inner_radius = radius.saturating_sub(thickness);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let coverage = rounded_rect_coverage(rect, radius, x, y)
+                    - rounded_rect_coverage(&inner, inner_radius, x, y);
+                if coverage > 0.0 {
+                    self.blend(x, y, color, coverage);
+                }
+            }
+        }
+    }
+
+    /// Copies the `src` part of `image` scaled onto `dst`, like `SDL_RenderCopy`,
+    /// with `alpha` applied on top of the image's own.
+    pub fn draw_image(&mut self, image: &Canvas, src: Rect, dst: Rect, alpha: u8) {
+        if src.width == 0 || src.height == 0 || alpha == 0 {
+            return;
+        }
+        let Some((x0, y0, x1, y1)) = self.visible_area(&dst) else {
+            return;
+        };
+        let scale_x = src.width as f32 / dst.width as f32;
+        let scale_y = src.height as f32 / dst.height as f32;
+        for y in y0..y1 {
+            let src_y = src.y + ((y - dst.y) as f32 * scale_y) as i32;
+            if src_y < 0 || src_y >= image.height as i32 {
+                continue;
+            }
+            for x in x0..x1 {
+                let src_x = src.x + ((x - dst.x) as f32 * scale_x) as i32;
+                if src_x < 0 || src_x >= image.width as i32 {
+                    continue;
+                }
+                let color = image.pixel(src_x as u32, src_y as u32);
+                if color.a > 0 {
+                    self.blend(x, y, color, alpha as f32 / 255.0);
+                }
+            }
+        }
+    }
+
+    /// Part of `rect` inside both the canvas and the clip, as `(x0, y0, x1, y1)`.
+    fn visible_area(&self, rect: &Rect) -> Option<(i32, i32, i32, i32)> {
+        let bounds = self
+            .clip
+            .unwrap_or(Rect::new(0, 0, self.width, self.height));
+        let x0 = rect.x.max(bounds.x).max(0);
+        let y0 = rect.y.max(bounds.y).max(0);
+        let x1 = (rect.x + rect.width as i32)
+            .min(bounds.x + bounds.width as i32)
+            .min(self.width as i32);
+        let y1 = (rect.y + rect.height as i32)
+            .min(bounds.y + bounds.height as i32)
+            .min(self.height as i32);
+        (x0 < x1 && y0 < y1).then_some((x0, y0, x1, y1))
+    }
+
+    /// Draws `color` over the pixel at `x`, `y`, `coverage` scaling its alpha.
+    fn blend(&mut self, x: i32, y: i32, color: Color, coverage: f32) {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return;
+        }
+        let index = y as usize * self.width as usize + x as usize;
+        let dst = self.pixels[index];
+
+        let src_alpha = color.a as f32 / 255.0 * coverage.clamp(0.0, 1.0);
+        let dst_alpha = dst.a as f32 / 255.0 * (1.0 - src_alpha);
+        let alpha = src_alpha + dst_alpha;
+        if alpha <= 0.0 {
+            return;
+        }
+        let mix = |src: u8, dst: u8| {
+            ((src as f32 * src_alpha + dst as f32 * dst_alpha) / alpha).round() as u8
+        };
+        self.pixels[index] = Color::rgba(
+            mix(color.r, dst.r),
+            mix(color.g, dst.g),
+            mix(color.b, dst.b),
+            (alpha * 255.0).round() as u8,
+        );
+    }
+}
+
+/// How much of the pixel at `x`, `y` lies inside a rect with rounded corners.
+/// Only the corners are antialiased, the straight edges are on pixel boundaries.
+fn rounded_rect_coverage(rect: &Rect, radius: u32, x: i32, y: i32) -> f32 {
+    let right = rect.x + rect.width as i32;
+    let bottom = rect.y + rect.height as i32;
+    if x < rect.x || y < rect.y || x >= right || y >= bottom {
+        return 0.0;
+    }
+
+    let radius = radius.min(rect.width / 2).min(rect.height / 2) as f32;
+    let px = x as f32 + 0.5;
+    let py = y as f32 + 0.5;
+    let corner_x = px.clamp(rect.x as f32 + radius, right as f32 - radius);
+    let corner_y = py.clamp(rect.y as f32 + radius, bottom as f32 - radius);
+    let distance = ((px - corner_x).powi(2) + (py - corner_y).powi(2)).sqrt();
+    if distance == 0.0 {
+        return 1.0; // Away from the corners.
+    }
+    (radius - distance + 0.5).clamp(0.0, 1.0)
+}
+
+/// The fonts for every role in `FontRole::DRAWN`, spaced as configured.
+pub fn load_fonts(
+    settings: &ClockSettings,
+) -> Result<HashMap<FontRole, GlyphFont>, FlipClockError> {
+    FontRole::DRAWN
+        .into_iter()
+        .map(|role| {
+            let config = role.config(settings);
+            let font = GlyphFont::load(&role.file(settings)?)?.spaced(
+                config.letter_spacing,
+                config.tabular_figures.then(|| settings.numerals.digits()),
+            );
+            Ok((role, font))
+        })
+        .collect()
+}
+
+/// A TrueType or OpenType font rasterized in software.
+pub struct GlyphFont {
+    font: FontVec,
+    /// Extra space between characters, as a fraction of the font size.
+    letter_spacing: f32,
+    /// Digits set in cells as wide as the widest of them.
+    tabular_figures: Option<String>,
+}
+
+impl GlyphFont {
+    pub fn load(path: &str) -> Result<Self, FlipClockError> {
+        let data = std::fs::read(path).map_err(|e| FlipClockError::font(path, e))?;
+        let font = FontVec::try_from_vec(data).map_err(|e| FlipClockError::font(path, e))?;
+        Ok(GlyphFont {
+            font,
+            letter_spacing: 0.0,
+            tabular_figures: None,
+        })
+    }
+
+    /// Lays out text with `letter_spacing` between characters and every one
+    /// of the digits in `tabular_figures` as wide as the widest of them.
+    pub fn spaced(mut self, letter_spacing: f32, tabular_figures: Option<&str>) -> Self {
+        self.letter_spacing = letter_spacing;
+        self.tabular_figures = tabular_figures.map(str::to_string);
+        self
+    }
+
+    /// The family name in the font's naming table, e.g. `DejaVu Sans`.
+    pub fn family_name(&self) -> Option<String> {
+        let face = ttf_parser::Face::parse(self.font.as_slice(), 0).ok()?;
+        face.names()
+            .into_iter()
+            .filter(|name| name.name_id == ttf_parser::name_id::FAMILY && name.is_unicode())
+            .find_map(|name| name.to_string())
+    }
+
+    pub fn has_glyph(&self, c: char) -> bool {
+        self.font.glyph_id(c).0 != 0
+    }
+
+    /// Size of the image `render` would return.
+    pub fn measure(&self, text: &str, size: f32) -> (u32, u32) {
+        let (_, width, height) = self.layout(text, size);
+        (width, height)
+    }
+
+    /// Where each character of `text` starts, as `render` places them.
+    pub fn positions(&self, text: &str, size: f32) -> Vec<f32> {
+        let (glyphs, _, _) = self.layout(text, size);
+        glyphs.iter().map(|glyph| glyph.position.x).collect()
+    }
+
+    /// Top and bottom of the ink of `text` as `render` draws it, from the top
+    /// of the line.
+    pub fn ink_bounds(&self, text: &str, size: f32) -> Option<(f32, f32)> {
+        let (glyphs, _, _) = self.layout(text, size);
+        glyphs
+            .into_iter()
+            .filter_map(|glyph| self.font.outline_glyph(glyph))
+            .map(|outlined| outlined.px_bounds())
+            .map(|bounds| (bounds.min.y, bounds.max.y))
+            .reduce(|(top, bottom), (glyph_top, glyph_bottom)| {
+                (top.min(glyph_top), bottom.max(glyph_bottom))
+            })
+    }
+
+    /// Distance from the top of a rendered line to its baseline.
+    pub fn ascent(&self, size: f32) -> f32 {
+        self.font.as_scaled(self.scale(size)).ascent()
+    }
+
+    /// `text` on a transparent image one line high, like SDL_ttf's blended
+    /// rendering. `size` is in pixels per em, which is what SDL_ttf's point
+    /// size comes down to.
+    pub fn render(&self, text: &str, size: f32, color: Color) -> Canvas {
+        let (glyphs, width, height) = self.layout(text, size);
+        let mut image = Canvas::new(width, height);
+        for glyph in glyphs {
+            if let Some(outlined) = self.font.outline_glyph(glyph) {
+                let bounds = outlined.px_bounds();
+                outlined.draw(|glyph_x, glyph_y, coverage| {
+                    image.blend(
+                        bounds.min.x as i32 + glyph_x as i32,
+                        bounds.min.y as i32 + glyph_y as i32,
+                        color,
+                        coverage,
+                    );
+                });
+            }
+        }
+        image
+    }
+
+    /// Outlines of `text` as SVG path data, placed as `render` would draw them.
+    pub fn path(&self, text: &str, size: f32) -> String {
+        let (glyphs, _, _) = self.layout(text, size);
+        let scaled = self.font.as_scaled(self.scale(size));
+        let (scale_x, scale_y) = (scaled.h_scale_factor(), scaled.v_scale_factor());
+
+        let mut path = String::new();
+        for glyph in glyphs {
+            let Some(outline) = self.font.outline(glyph.id) else {
+                continue;
+            };
+            // Font units point up from the baseline, SVG's y axis points down.
+            let to_px = |p: ab_glyph::Point| {
+                (
+                    glyph.position.x + p.x * scale_x,
+                    glyph.position.y - p.y * scale_y,
+                )
+            };
+            let mut pen = None;
+            for curve in outline.curves {
+                let (start, command) = match curve {
+                    OutlineCurve::Line(a, b) => {
+                        let b = to_px(b);
+                        (a, format!("L{:.2} {:.2}", b.0, b.1))
+                    }
+                    OutlineCurve::Quad(a, b, c) => {
+                        let (b, c) = (to_px(b), to_px(c));
+                        (a, format!("Q{:.2} {:.2} {:.2} {:.2}", b.0, b.1, c.0, c.1))
+                    }
+                    OutlineCurve::Cubic(a, b, c, d) => {
+                        let (b, c, d) = (to_px(b), to_px(c), to_px(d));
+                        (
+                            a,
+                            format!(
+                                "C{:.2} {:.2} {:.2} {:.2} {:.2} {:.2}",
+                                b.0, b.1, c.0, c.1, d.0, d.1
+                            ),
+                        )
+                    }
+                };
+                if pen != Some(start) {
+                    let start_px = to_px(start);
+                    path.push_str(&format!("M{:.2} {:.2}", start_px.0, start_px.1));
+                }
+                path.push_str(&command);
+                pen = Some(match curve {
+                    OutlineCurve::Line(_, end)
+                    | OutlineCurve::Quad(_, _, end)
+                    | OutlineCurve::Cubic(_, _, _, end) => end,
+                });
+            }
+        }
+        path
+    }
+
+    fn scale(&self, size: f32) -> PxScale {
+        let units_per_em = self.font.units_per_em().unwrap_or(1000.0);
+        PxScale::from(size * self.font.height_unscaled() / units_per_em)
+    }
+
+    /// Glyphs of `text` on one line, and the size of that line in pixels.
+    fn layout(&self, text: &str, size: f32) -> (Vec<Glyph>, u32, u32) {
+        let scale = self.scale(size);
+        let font = self.font.as_scaled(scale);
+
+        let figure_width = self.tabular_figures.as_ref().map(|figures| {
+            figures
+                .chars()
+                .map(|c| font.h_advance(font.glyph_id(c)))
+                .fold(0.0, f32::max)
+        });
+        let spacing = self.letter_spacing * size;
+
+        let mut glyphs = Vec::new();
+        let mut x = 0.0;
+        let mut previous = None;
+        for (index, c) in text.chars().enumerate() {
+            let id = font.glyph_id(c);
+            if index > 0 {
+                x += spacing;
+            }
+            let is_figure = self
+                .tabular_figures
+                .as_ref()
+                .is_some_and(|figures| figures.contains(c));
+            match figure_width.filter(|_| is_figure) {
+                // Centred in a cell of its own, without kerning against its neighbours.
+                Some(figure_width) => {
+                    let offset = (figure_width - font.h_advance(id)) / 2.0;
+                    glyphs
+                        .push(id.with_scale_and_position(scale, point(x + offset, font.ascent())));
+                    x += figure_width;
+                    previous = None;
+                }
+                None => {
+                    if let Some(previous) = previous {
+                        x += font.kern(previous, id);
+                    }
+                    glyphs.push(id.with_scale_and_position(scale, point(x, font.ascent())));
+                    x += font.h_advance(id);
+                    previous = Some(id);
+                }
+            }
+        }
+
+        let width = (x.ceil() as u32).max(1);
+        let height = ((font.ascent() - font.descent()).ceil() as u32).max(1);
+        (glyphs, width, height)
+    }
+}
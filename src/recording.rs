@@ -0,0 +1,247 @@
+use crate::config::{ClockSettings, RgbColor};
+use crate::error::FlipClockError;
+use crate::export;
+use crate::graphics_engine::Frame;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Largest palette both GIF and indexed PNG can hold.
+const PALETTE_SIZE: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordingFormat {
+    Gif,
+    /// Animated PNG.
+    Apng,
+}
+
+impl RecordingFormat {
+    /// Picks the format from the extension of `path`.
+    pub fn from_path(path: &Path) -> Result<Self, FlipClockError> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("gif") => Ok(RecordingFormat::Gif),
+            Some("png" | "apng") => Ok(RecordingFormat::Apng),
+            _ => Err(FlipClockError::Usage(format!(
+                "Unknown recording format, use .gif or .png: {}",
+                path.display()
+            ))),
+        }
+    }
+}
+
+/// The theme's colors and evenly spaced blends between every pair of them,
+/// which is all that antialiased edges, fades and dimming ever produce.
+struct Palette {
+    colors: Vec<[u8; 3]>,
+    /// Palette index of every color seen so far.
+    lookup: HashMap<[u8; 3], u8>,
+}
+
+impl Palette {
+    fn for_theme(settings: &ClockSettings) -> Self {
+        let rgb = |color: RgbColor| [color.r, color.g, color.b];
+        let mut theme = Vec::new();
+        for color in [
+            rgb(settings.background_color),
+            rgb(settings.card_color),
+            rgb(settings.card_border_color),
+            rgb(settings.font_color),
+            [0, 0, 0], // Dimming.
+        ] {
+            if !theme.contains(&color) {
+                theme.push(color);
+            }
+        }
+
+        let pairs: Vec<_> = (0..theme.len())
+            .flat_map(|a| (a + 1..theme.len()).map(move |b| (a, b)))
+            .collect();
+        let steps = (PALETTE_SIZE - theme.len()) / pairs.len().max(1);
+
+        let mut colors = theme.clone();
+        for (a, b) in pairs {
+            for step in 1..=steps {
+                let t = step as f32 / (steps + 1) as f32;
+                let blend = |channel: usize| {
+                    (theme[a][channel] as f32 * (1.0 - t) + theme[b][channel] as f32 * t).round()
+                        as u8
+                };
+                let color = [blend(0), blend(1), blend(2)];
+                if !colors.contains(&color) {
+                    colors.push(color);
+                }
+            }
+        }
+
+        let lookup = colors
+            .iter()
+            .enumerate()
+            .map(|(index, color)| (*color, index as u8))
+            .collect();
+        Palette { colors, lookup }
+    }
+
+    fn index(&mut self, color: [u8; 3]) -> u8 {
+        let colors = &self.colors;
+        *self.lookup.entry(color).or_insert_with(|| {
+            let distance = |other: &[u8; 3]| -> i32 {
+                (0..3)
+                    .map(|channel| (color[channel] as i32 - other[channel] as i32).pow(2))
+                    .sum()
+            };
+            (0..colors.len())
+                .min_by_key(|&index| distance(&colors[index]))
+                .unwrap_or(0) as u8
+        })
+    }
+
+    fn to_rgb(&self) -> Vec<u8> {
+        self.colors.iter().flatten().copied().collect()
+    }
+}
+
+struct RecordedFrame {
+    indices: Vec<u8>,
+    /// When the frame was drawn, from the start of the recording.
+    at: Duration,
+}
+
+/// Frames drawn during a recording, reduced to the theme's palette as they come in.
+pub struct Recording {
+    started: Instant,
+    size: Option<(u32, u32)>,
+    palette: Palette,
+    frames: Vec<RecordedFrame>,
+}
+
+impl Recording {
+    pub fn new(settings: &ClockSettings, started: Instant) -> Self {
+        Recording {
+            started,
+            size: None,
+            palette: Palette::for_theme(settings),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Adds a frame drawn at `at`. A frame identical to the one before only
+    /// keeps that one on screen for longer.
+    pub fn push(&mut self, frame: &Frame, at: Instant) -> Result<(), FlipClockError> {
+        let size = *self.size.get_or_insert((frame.width, frame.height));
+        if size != (frame.width, frame.height) {
+            return Err(FlipClockError::Render(
+                "The size changed during the recording".to_string(),
+            ));
+        }
+
+        let indices: Vec<u8> = frame
+            .pixels
+            .chunks_exact(4)
+            .map(|pixel| self.palette.index([pixel[0], pixel[1], pixel[2]]))
+            .collect();
+        if self
+            .frames
+            .last()
+            .is_some_and(|previous| previous.indices == indices)
+        {
+            return Ok(());
+        }
+        self.frames.push(RecordedFrame {
+            indices,
+            at: at.saturating_duration_since(self.started),
+        });
+        Ok(())
+    }
+
+    /// Writes the frames to `path`, each shown until the next one and the last until `end`.
+    pub fn save(
+        &self,
+        path: &Path,
+        format: RecordingFormat,
+        end: Instant,
+    ) -> Result<(), FlipClockError> {
+        let Some((width, height)) = self.size else {
+            return Err(FlipClockError::Render(
+                "Nothing was drawn during the recording".to_string(),
+            ));
+        };
+        let end = end.saturating_duration_since(self.started);
+        export::write_atomically(path, |writer| match format {
+            RecordingFormat::Gif => self.write_gif(writer, width, height, end),
+            RecordingFormat::Apng => self.write_apng(writer, width, height, end),
+        })
+    }
+
+    /// When each frame is replaced by the next one.
+    fn frame_ends(&self, end: Duration) -> impl Iterator<Item = Duration> + '_ {
+        self.frames
+            .iter()
+            .skip(1)
+            .map(|frame| frame.at)
+            .chain(std::iter::once(end))
+    }
+
+    fn write_gif(
+        &self,
+        writer: &mut BufWriter<File>,
+        width: u32,
+        height: u32,
+        end: Duration,
+    ) -> io::Result<()> {
+        let (width, height) = (
+            u16::try_from(width).map_err(io::Error::other)?,
+            u16::try_from(height).map_err(io::Error::other)?,
+        );
+        let mut encoder = gif::Encoder::new(writer, width, height, &self.palette.to_rgb())
+            .map_err(io::Error::other)?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(io::Error::other)?;
+
+        // GIF delays are in centiseconds, rounding each frame's start and end
+        // keeps the errors from adding up.
+        let centiseconds = |time: Duration| (time.as_millis() as f64 / 10.0).round() as u64;
+        for (frame, frame_end) in self.frames.iter().zip(self.frame_ends(end)) {
+            let delay = centiseconds(frame_end).saturating_sub(centiseconds(frame.at));
+            let gif_frame = gif::Frame {
+                width,
+                height,
+                delay: delay.min(u16::MAX as u64) as u16,
+                buffer: (&frame.indices[..]).into(),
+                ..gif::Frame::default()
+            };
+            encoder.write_frame(&gif_frame).map_err(io::Error::other)?;
+        }
+        Ok(())
+    }
+
+    fn write_apng(
+        &self,
+        writer: &mut BufWriter<File>,
+        width: u32,
+        height: u32,
+        end: Duration,
+    ) -> io::Result<()> {
+        let mut encoder = png::Encoder::new(writer, width, height);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_palette(self.palette.to_rgb());
+        encoder.set_animated(self.frames.len() as u32, 0)?;
+        let mut writer = encoder.write_header()?;
+
+        for (frame, frame_end) in self.frames.iter().zip(self.frame_ends(end)) {
+            let delay = frame_end.saturating_sub(frame.at).as_millis();
+            writer.set_frame_delay(delay.min(u16::MAX as u128) as u16, 1000)?;
+            writer.write_image_data(&frame.indices)?;
+        }
+        writer.finish()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+#[cfg(feature = "sdl")]
+use crate::cli::{CliOptions, parse_window_id};
+#[cfg(feature = "sdl")]
+use flipclock::{FlipClockError, x11};
+
+#[cfg(feature = "sdl")]
+const XSCREENSAVER_WINDOW_VAR: &str = "XSCREENSAVER_WINDOW";
+
+/// The foreign window to draw into, if we were started as a screensaver.
+/// An explicit `-window-id` wins over `-root`, which in turn prefers the
+/// virtual root XScreenSaver exports over the real root window.
+#[cfg(feature = "sdl")]
+pub fn target_window(options: &CliOptions) -> Result<Option<u64>, FlipClockError> {
+    if let Some(window_id) = options.window_id {
+        return Ok(Some(window_id));
+    }
+
+    let exported = std::env::var(XSCREENSAVER_WINDOW_VAR).ok();
+    match exported {
+        Some(value) => parse_window_id(&value)
+            .map(Some)
+            .map_err(FlipClockError::Usage),
+        None if options.root => x11::root_window().map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Stanza for XScreenSaver's `hacks/config` directory.
+pub fn xscreensaver_config() -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="ISO-8859-1"?>
+
+<screensaver name="{name}" _label="Flip Clock">
+  <command arg="-root"/>
+
+  <_description>
+A flip clock with card-flip animations, themed through
+~/.config/flipclock/config.toml.
+
+Add it to the programs list in ~/.xscreensaver with:
+  "Flip Clock"  {name} -root
+  </_description>
+</screensaver>
+"#,
+        name = env!("CARGO_PKG_NAME")
+    )
+}
+
+/// Desktop entry for screensaver frameworks that discover hacks through
+/// `.desktop` files (e.g. `/usr/share/applications/screensavers`).
+pub fn desktop_entry() -> String {
+    format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=Flip Clock\n\
+         Comment=A flip clock with card-flip animations\n\
+         TryExec={name}\n\
+         Exec={name} -root\n\
+         NoDisplay=true\n\
+         Categories=Screensaver;\n",
+        name = env!("CARGO_PKG_NAME")
+    )
+}
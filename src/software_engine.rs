@@ -0,0 +1,458 @@
+use crate::config::{Action, AmPmStyle, AnimationStyle, ClockSettings, InputResponse, RgbColor};
+use crate::error::FlipClockError;
+use crate::graphics_engine::{
+    AnimationState, ClockLayout, Color, Flap, FontRole, Frame, GraphicsEngine, Rect,
+    TimeDigitPosition, am_pm_dot_size, am_pm_position, digit_split, easing_function,
+};
+use crate::input::{InputEvent, InputPolicy};
+use crate::keybindings::{KeyCombo, Keybindings};
+use crate::locale;
+use crate::raster::{self, Canvas, GlyphFont};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Instant;
+
+/// Rendered strings kept around before the cache starts over.
+const TEXT_CACHE_SIZE: usize = 256;
+
+/// Something that happened on a surface since the last frame.
+pub enum SurfaceEvent {
+    Key(KeyCombo),
+    Input(InputEvent),
+    Resized,
+    Quit,
+}
+
+/// Where a software rendered clock is shown and where its input comes from,
+/// such as a terminal or a framebuffer device.
+pub trait Surface {
+    /// Size of the surface in pixels.
+    fn size(&self) -> Result<(u32, u32), FlipClockError>;
+
+    fn present(&mut self, canvas: &Canvas) -> Result<(), FlipClockError>;
+
+    fn poll_events(&mut self) -> Result<Vec<SurfaceEvent>, FlipClockError>;
+}
+
+/// A surface that only exists in memory, for screenshots without a display.
+pub struct Offscreen {
+    width: u32,
+    height: u32,
+}
+
+impl Offscreen {
+    pub fn new(width: u32, height: u32) -> Self {
+        Offscreen { width, height }
+    }
+}
+
+impl Surface for Offscreen {
+    fn size(&self) -> Result<(u32, u32), FlipClockError> {
+        Ok((self.width, self.height))
+    }
+
+    fn present(&mut self, _canvas: &Canvas) -> Result<(), FlipClockError> {
+        Ok(())
+    }
+
+    fn poll_events(&mut self) -> Result<Vec<SurfaceEvent>, FlipClockError> {
+        Ok(Vec::new())
+    }
+}
+
+/// Draws the clock into a `Canvas` on the CPU and hands every frame to a
+/// `Surface`. Cards and animations look like those of the SDL engine.
+pub struct SoftwareEngine<S: Surface> {
+    surface: S,
+    settings: ClockSettings,
+    canvas: Canvas,
+    fonts: HashMap<FontRole, GlyphFont>,
+    /// Rendered strings by font, text and pixel size, dropped whenever a font or its color changes.
+    text_cache: HashMap<(FontRole, String, u32), Rc<Canvas>>,
+    input_policy: InputPolicy,
+    keybindings: Keybindings,
+    started: Instant,
+}
+
+impl<S: Surface> SoftwareEngine<S> {
+    pub fn new(surface: S, settings: &ClockSettings) -> Result<Self, FlipClockError> {
+        let (width, height) = surface.size()?;
+        Ok(SoftwareEngine {
+            surface,
+            settings: settings.clone(),
+            canvas: Canvas::new(width, height),
+            fonts: raster::load_fonts(settings)?,
+            text_cache: HashMap::new(),
+            input_policy: InputPolicy::new(settings),
+            keybindings: Keybindings::parse(&settings.keybindings)?,
+            started: Instant::now(),
+        })
+    }
+
+    fn resize(&mut self) -> Result<(), FlipClockError> {
+        let (width, height) = self.surface.size()?;
+        if (width, height) != (self.canvas.width(), self.canvas.height()) {
+            self.canvas = Canvas::new(width, height);
+            self.text_cache.clear();
+        }
+        Ok(())
+    }
+
+    fn to_color(color: RgbColor) -> Color {
+        Color::rgb(color.r, color.g, color.b)
+    }
+
+    fn format_time(&self, time: u32, position: TimeDigitPosition) -> String {
+        locale::format_value(time, position, &self.settings)
+    }
+
+    fn text(&mut self, role: FontRole, text: &str, size: u32) -> Rc<Canvas> {
+        if self.text_cache.len() >= TEXT_CACHE_SIZE {
+            self.text_cache.clear();
+        }
+        let color = Self::to_color(role.color(&self.settings));
+        let font = &self.fonts[&role];
+        self.text_cache
+            .entry((role, text.to_string(), size))
+            .or_insert_with(|| Rc::new(font.render(text, size as f32, color)))
+            .clone()
+    }
+
+    /// Text is sized for the layout's cards, not for a card sliding between two layouts.
+    fn font_size(&self, role: FontRole) -> u32 {
+        role.size(self.calculate_layout().rect_size, &self.settings)
+    }
+
+    fn render_digits(
+        &mut self,
+        current_time: u32,
+        position: TimeDigitPosition,
+        rect: &Rect,
+        animation: Option<&AnimationState>,
+    ) -> Result<(), FlipClockError> {
+        let gap_height = self.settings.card_gap;
+        let top_half_height = (rect.height as i32 - gap_height) / 2;
+        let card_color = Self::to_color(self.settings.card_color);
+        self.draw_card(rect);
+
+        self.canvas.set_clip(Some(*rect));
+        match animation.filter(|a| a.is_animating && a.previous_value.is_some()) {
+            Some(animation) => match animation.style {
+                AnimationStyle::Flip | AnimationStyle::Rattle => {
+                    let (from, to, step_progress) = animation.current_step();
+                    let past_time_str = self.format_time(from, position);
+                    let time_str = self.format_time(to, position);
+                    self.render_flip(&time_str, &past_time_str, rect, step_progress);
+                }
+                AnimationStyle::Slide | AnimationStyle::Roll => {
+                    self.render_roll(&animation.sequence(), position, rect, animation.progress);
+                }
+                AnimationStyle::Fade => {
+                    let (from, to, step_progress) = animation.current_step();
+                    let alpha = (easing_function(step_progress) * 255.0) as u8;
+                    let past_time_str = self.format_time(from, position);
+                    let time_str = self.format_time(to, position);
+                    self.render_static_digit(&past_time_str, rect, 0, 255 - alpha);
+                    self.render_static_digit(&time_str, rect, 0, alpha);
+                }
+                AnimationStyle::None => {
+                    let time_str = self.format_time(current_time, position);
+                    self.render_static_digit(&time_str, rect, 0, 255);
+                }
+            },
+            None => {
+                let time_str = self.format_time(current_time, position);
+                self.render_static_digit(&time_str, rect, 0, 255);
+            }
+        }
+
+        let gap_rect = Rect::new(
+            rect.x,
+            rect.y + top_half_height,
+            rect.width,
+            gap_height.max(0) as u32,
+        );
+        self.canvas.fill_rect(&gap_rect, card_color);
+        self.canvas.set_clip(None);
+        Ok(())
+    }
+
+    fn draw_card(&mut self, rect: &Rect) {
+        let corner_radius = if self.settings.card_rounded_corners {
+            rect.height / 10
+        } else {
+            0
+        };
+        self.canvas.fill_rounded_rect(
+            rect,
+            corner_radius,
+            Self::to_color(self.settings.card_color),
+        );
+        self.canvas.stroke_rounded_rect(
+            rect,
+            corner_radius,
+            self.settings.card_border_size,
+            Self::to_color(self.settings.card_border_color),
+        );
+    }
+
+    /// `time_str` rendered, with its halves on the card `rect`.
+    fn digit_halves(&mut self, time_str: &str, rect: &Rect) -> (Rc<Canvas>, [Flap; 2]) {
+        let rect_size = self.calculate_layout().rect_size;
+        let size = FontRole::Digit.size(rect_size, &self.settings);
+        let digit = self.text(FontRole::Digit, time_str, size);
+        let (ink_top, ink_bottom) = self.fonts[&FontRole::Digit]
+            .ink_bounds(self.settings.numerals.digits(), size as f32)
+            .unwrap_or((0.0, digit.height() as f32));
+        let split = digit_split(ink_top, ink_bottom, size, &self.settings);
+        let halves = Flap::halves(
+            rect,
+            self.settings.card_gap,
+            digit.width(),
+            digit.height(),
+            split,
+            rect.height as f32 / rect_size.max(1) as f32,
+        );
+        (digit, halves)
+    }
+
+    fn draw_flap(&mut self, image: &Canvas, flap: Flap, alpha: u8) {
+        self.canvas.draw_image(image, flap.src, flap.dst, alpha);
+    }
+
+    /// Draws a digit at rest, split in two halves around the card gap.
+    fn render_static_digit(&mut self, time_str: &str, rect: &Rect, y_offset: i32, alpha: u8) {
+        let (digit, halves) = self.digit_halves(time_str, rect);
+        for half in halves {
+            self.draw_flap(&digit, half.offset(y_offset), alpha);
+        }
+    }
+
+    /// Scrolls the card upwards through `values`, one card height per value.
+    fn render_roll(
+        &mut self,
+        values: &[u32],
+        position: TimeDigitPosition,
+        rect: &Rect,
+        progress: f32,
+    ) {
+        let steps = values.len().saturating_sub(1) as f32;
+        let scrolled = easing_function(progress) * steps * rect.height as f32;
+
+        for (index, value) in values.iter().enumerate() {
+            let y_offset = (index as f32 * rect.height as f32 - scrolled) as i32;
+            if y_offset.unsigned_abs() >= rect.height {
+                continue;
+            }
+            let time_str = self.format_time(*value, position);
+            self.render_static_digit(&time_str, rect, y_offset, 255);
+        }
+    }
+
+    /// The top half of the old value folds down onto the gap, then the
+    /// bottom half of the new value unfolds from it.
+    fn render_flip(&mut self, time_str: &str, past_time_str: &str, rect: &Rect, progress: f32) {
+        let gap_height = self.settings.card_gap;
+        let top_half_height = (rect.height as i32 - gap_height) / 2;
+        let bottom_half_height = rect.height as i32 - top_half_height - gap_height;
+        let gap_top = rect.y + top_half_height;
+        let bottom_y = gap_top + gap_height;
+        let card_bottom = rect.y + rect.height as i32;
+
+        let (current, [current_top, current_bottom]) = self.digit_halves(time_str, rect);
+        let (past, [past_top, past_bottom]) = self.digit_halves(past_time_str, rect);
+
+        let eased_progress = easing_function(progress);
+        if eased_progress < 0.5 {
+            let revealed_height = (top_half_height as f32 * eased_progress * 2.0) as i32;
+            self.draw_flap(
+                &current,
+                current_top.clipped(rect.y, rect.y + revealed_height),
+                255,
+            );
+            self.draw_flap(
+                &past,
+                past_top.folded(gap_top, 1.0 - eased_progress * 2.0),
+                255,
+            );
+            self.draw_flap(&past, past_bottom, 255);
+        } else {
+            self.draw_flap(&current, current_top, 255);
+
+            let bottom_flip_progress = (eased_progress - 0.5) * 2.0;
+            let old_visible_height =
+                (bottom_half_height as f32 * (1.0 - bottom_flip_progress)).max(0.0) as i32;
+            self.draw_flap(
+                &past,
+                past_bottom.clipped(card_bottom - old_visible_height, card_bottom),
+                255,
+            );
+
+            let new_revealed_height =
+                (bottom_half_height as f32 * bottom_flip_progress).max(0.0) as i32;
+            self.draw_flap(
+                &current,
+                current_bottom.clipped(bottom_y, bottom_y + new_revealed_height),
+                255,
+            );
+        }
+    }
+}
+
+impl<S: Surface> GraphicsEngine for SoftwareEngine<S> {
+    fn clear(&mut self) -> Result<(), FlipClockError> {
+        self.canvas
+            .fill(Self::to_color(self.settings.background_color));
+        Ok(())
+    }
+
+    fn present(&mut self) -> Result<(), FlipClockError> {
+        if self.settings.dim_level > 0.0 {
+            let alpha = (self.settings.dim_level.clamp(0.0, 1.0) * 255.0) as u8;
+            let screen = Rect::new(0, 0, self.canvas.width(), self.canvas.height());
+            self.canvas.fill_rect(&screen, Color::rgba(0, 0, 0, alpha));
+        }
+        self.surface.present(&self.canvas)
+    }
+
+    fn draw_rect(
+        &mut self,
+        rect: &Rect,
+        color: Color,
+        border_color: Option<Color>,
+        border_width: u32,
+        radius: i32,
+        filled: bool,
+    ) -> Result<(), FlipClockError> {
+        let radius = radius.max(0) as u32;
+        if filled {
+            self.canvas.fill_rounded_rect(rect, radius, color);
+        }
+        if let Some(border_color) = border_color {
+            self.canvas
+                .stroke_rounded_rect(rect, radius, border_width, border_color);
+        }
+        Ok(())
+    }
+
+    fn render_digit(
+        &mut self,
+        value: u32,
+        position: TimeDigitPosition,
+        rect: &Rect,
+        animation: Option<AnimationState>,
+    ) -> Result<(), FlipClockError> {
+        self.render_digits(value, position, rect, animation.as_ref())
+    }
+
+    fn render_date(&mut self, rect: &Rect, text: &str) -> Result<(), FlipClockError> {
+        self.draw_card(rect);
+        let size = self.font_size(FontRole::Label);
+        let label = self.text(FontRole::Label, text, size);
+        let flap = Flap::centred(
+            rect,
+            label.width(),
+            label.height(),
+            FontRole::Label.vertical_offset(size, &self.settings),
+        );
+        self.canvas.set_clip(Some(*rect));
+        self.draw_flap(&label, flap, 255);
+        self.canvas.set_clip(None);
+        Ok(())
+    }
+
+    fn render_am_pm_indicator(
+        &mut self,
+        hour_rect: &Rect,
+        cards: &Rect,
+        is_pm: bool,
+    ) -> Result<(), FlipClockError> {
+        if self.settings.use_24hour {
+            return Ok(());
+        }
+        let size = self.font_size(FontRole::Indicator);
+        match self.settings.am_pm_style {
+            AmPmStyle::Text => {
+                let text = self.settings.am_pm_labels[is_pm as usize].clone();
+                let label = self.text(FontRole::Indicator, &text, size);
+                let (width, height) = (label.width(), label.height());
+                if let Some((x, y)) =
+                    am_pm_position(&self.settings, hour_rect, cards, is_pm, width, height)
+                {
+                    let y = y + FontRole::Indicator.vertical_offset(size, &self.settings);
+                    self.canvas.draw_image(
+                        &label,
+                        Rect::new(0, 0, width, height),
+                        Rect::new(x, y, width, height),
+                        255,
+                    );
+                }
+            }
+            AmPmStyle::Dot if is_pm => {
+                let dot = am_pm_dot_size(size);
+                if let Some((x, y)) =
+                    am_pm_position(&self.settings, hour_rect, cards, is_pm, dot, dot)
+                {
+                    let color = Self::to_color(FontRole::Indicator.color(&self.settings));
+                    self.canvas
+                        .fill_rounded_rect(&Rect::new(x, y, dot, dot), dot / 2, color);
+                }
+            }
+            AmPmStyle::Dot => {}
+        }
+        Ok(())
+    }
+
+    fn handle_events(&mut self) -> Result<Vec<Action>, FlipClockError> {
+        let mut actions = Vec::new();
+        let mut inputs = Vec::new();
+
+        for event in self.surface.poll_events()? {
+            match event {
+                SurfaceEvent::Quit => actions.push(Action::Quit),
+                SurfaceEvent::Resized => self.resize()?,
+                SurfaceEvent::Key(combo) => match self.keybindings.action_for(&combo) {
+                    Some(action) => actions.push(action),
+                    None => inputs.push(InputEvent::Key),
+                },
+                SurfaceEvent::Input(input) => inputs.push(input),
+            }
+        }
+
+        match self
+            .input_policy
+            .respond_all(inputs, self.started.elapsed())
+        {
+            InputResponse::Exit => actions.push(Action::Quit),
+            InputResponse::Pause => actions.push(Action::TogglePause),
+            InputResponse::Ignore => {}
+        }
+
+        Ok(actions)
+    }
+
+    fn calculate_layout(&self) -> ClockLayout {
+        ClockLayout::calculate(self.canvas.width(), self.canvas.height(), &self.settings)
+    }
+
+    fn get_settings(&self) -> &ClockSettings {
+        &self.settings
+    }
+
+    fn update_settings(&mut self, settings: ClockSettings) -> Result<(), FlipClockError> {
+        if settings.font_changed(&self.settings) {
+            self.fonts = raster::load_fonts(&settings)?;
+        }
+        self.text_cache.clear();
+        self.settings = settings;
+        Ok(())
+    }
+
+    fn read_pixels(&mut self) -> Result<Frame, FlipClockError> {
+        Ok(Frame {
+            width: self.canvas.width(),
+            height: self.canvas.height(),
+            pixels: self.canvas.to_rgba(),
+        })
+    }
+}
@@ -0,0 +1,383 @@
+use crate::config::{Action, AmPmStyle, ClockSettings, FontStyle, RgbColor};
+use crate::error::FlipClockError;
+use crate::export;
+use crate::graphics_engine::{
+    AnimationState, ClockLayout, Color, Flap, FontRole, GraphicsEngine, Rect, TimeDigitPosition,
+    am_pm_dot_size, am_pm_position, digit_split,
+};
+use crate::locale;
+use crate::raster::{self, GlyphFont};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+/// Writes the clock face as an SVG document instead of pixels: the same
+/// cards, borders, gap and labels in the same places as the other engines,
+/// with the digits as text set in the theme's font or as glyph outlines.
+pub struct SvgEngine {
+    settings: ClockSettings,
+    width: u32,
+    height: u32,
+    fonts: HashMap<FontRole, GlyphFont>,
+    /// Glyph outlines instead of text referencing the font by name.
+    outline_text: bool,
+    elements: Vec<String>,
+}
+
+impl SvgEngine {
+    pub fn new(
+        width: u32,
+        height: u32,
+        settings: &ClockSettings,
+        outline_text: bool,
+    ) -> Result<Self, FlipClockError> {
+        Ok(SvgEngine {
+            settings: settings.clone(),
+            width,
+            height,
+            fonts: raster::load_fonts(settings)?,
+            outline_text,
+            elements: Vec::new(),
+        })
+    }
+
+    fn format_time(&self, time: u32, position: TimeDigitPosition) -> String {
+        locale::format_value(time, position, &self.settings)
+    }
+
+    fn font_size(&self, role: FontRole) -> u32 {
+        role.size(self.calculate_layout().rect_size, &self.settings)
+    }
+
+    /// `text` one line high with its top left corner at 0, 0, as the other
+    /// engines render it before copying it into place.
+    fn text(&self, role: FontRole, text: &str, size: u32) -> String {
+        let font = &self.fonts[&role];
+        let fill = hex(role.color(&self.settings));
+        if self.outline_text {
+            return format!(
+                r#"<path d="{}" fill="{}"/>"#,
+                font.path(text, size as f32),
+                fill
+            );
+        }
+
+        let config = role.config(&self.settings);
+        // Every character placed where the other engines draw it, when spacing moves them.
+        let x = if config.letter_spacing != 0.0 || config.tabular_figures {
+            font.positions(text, size as f32)
+                .iter()
+                .map(|x| format!("{:.2}", x))
+                .collect::<Vec<_>>()
+                .join(" ")
+        } else {
+            "0".to_string()
+        };
+        let mut face = String::new();
+        if let Some(weight) = config.weight {
+            face.push_str(&format!(r#" font-weight="{}""#, weight.css_value()));
+        }
+        match config.style {
+            FontStyle::Normal => {}
+            FontStyle::Italic => face.push_str(r#" font-style="italic""#),
+            FontStyle::Oblique => face.push_str(r#" font-style="oblique""#),
+        }
+        format!(
+            r#"<text x="{}" y="{:.2}" font-family="{}" font-size="{}"{} fill="{}">{}</text>"#,
+            x,
+            font.ascent(size as f32),
+            escape(
+                &font
+                    .family_name()
+                    .unwrap_or_else(|| "sans-serif".to_string())
+            ),
+            size,
+            face,
+            fill,
+            escape(text)
+        )
+    }
+
+    /// The `src` part of a line of text scaled onto `dst`, like the texture
+    /// copies of the SDL engine. A nested viewport both clips and scales.
+    fn copy_text(&mut self, role: FontRole, text: &str, size: u32, src: Rect, dst: Rect) {
+        if src.width == 0 || src.height == 0 || dst.width == 0 || dst.height == 0 {
+            return;
+        }
+        let element = format!(
+            r#"<svg x="{}" y="{}" width="{}" height="{}" viewBox="{} {} {} {}" preserveAspectRatio="none">{}</svg>"#,
+            dst.x,
+            dst.y,
+            dst.width,
+            dst.height,
+            src.x,
+            src.y,
+            src.width,
+            src.height,
+            self.text(role, text, size)
+        );
+        self.elements.push(element);
+    }
+
+    fn push_card(&mut self, rect: &Rect) {
+        let corner_radius = if self.settings.card_rounded_corners {
+            rect.height / 10
+        } else {
+            0
+        };
+        self.push_rect(
+            rect,
+            corner_radius,
+            Some(to_color(self.settings.card_color)),
+            None,
+        );
+        self.push_rect(
+            rect,
+            corner_radius,
+            None,
+            Some((
+                to_color(self.settings.card_border_color),
+                self.settings.card_border_size,
+            )),
+        );
+    }
+
+    /// A card with its digits at rest. Animations have no place in a still image.
+    fn render_digits(&mut self, current_time: u32, position: TimeDigitPosition, rect: &Rect) {
+        let gap_height = self.settings.card_gap;
+        let top_half_height = (rect.height as i32 - gap_height) / 2;
+        let card_color = to_color(self.settings.card_color);
+        self.push_card(rect);
+
+        // Everything drawn on the card is clipped to it.
+        self.elements.push(format!(
+            r#"<svg x="{0}" y="{1}" width="{2}" height="{3}" viewBox="{0} {1} {2} {3}">"#,
+            rect.x, rect.y, rect.width, rect.height
+        ));
+        let time_str = self.format_time(current_time, position);
+        let rect_size = self.calculate_layout().rect_size;
+        let size = FontRole::Digit.size(rect_size, &self.settings);
+        let font = &self.fonts[&FontRole::Digit];
+        let (digit_width, digit_height) = font.measure(&time_str, size as f32);
+        let (ink_top, ink_bottom) = font
+            .ink_bounds(self.settings.numerals.digits(), size as f32)
+            .unwrap_or((0.0, digit_height as f32));
+        let split = digit_split(ink_top, ink_bottom, size, &self.settings);
+        let scale = rect.height as f32 / rect_size.max(1) as f32;
+        for half in Flap::halves(rect, gap_height, digit_width, digit_height, split, scale) {
+            self.copy_text(FontRole::Digit, &time_str, size, half.src, half.dst);
+        }
+        let gap_rect = Rect::new(
+            rect.x,
+            rect.y + top_half_height,
+            rect.width,
+            gap_height.max(0) as u32,
+        );
+        self.push_rect(&gap_rect, 0, Some(card_color), None);
+        self.elements.push("</svg>".to_string());
+    }
+
+    /// A rect filled and/or with a border of the given width inside its edges.
+    fn push_rect(
+        &mut self,
+        rect: &Rect,
+        radius: u32,
+        fill: Option<Color>,
+        border: Option<(Color, u32)>,
+    ) {
+        if let Some(color) = fill {
+            self.elements.push(format!(
+                r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{}"{}/>"#,
+                rect.x,
+                rect.y,
+                rect.width,
+                rect.height,
+                radius,
+                paint("fill", color)
+            ));
+        }
+        if let Some((color, width)) = border.filter(|&(_, width)| width > 0) {
+            // SVG strokes are centered on the edge, so inset by half the width.
+            let inset = width as f32 / 2.0;
+            self.elements.push(format!(
+                r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{}" fill="none" stroke-width="{}"{}/>"#,
+                rect.x as f32 + inset,
+                rect.y as f32 + inset,
+                (rect.width as f32 - width as f32).max(0.0),
+                (rect.height as f32 - width as f32).max(0.0),
+                (radius as f32 - inset).max(0.0),
+                width,
+                paint("stroke", color)
+            ));
+        }
+    }
+
+    fn document(&self) -> String {
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n{2}\n</svg>\n",
+            self.width,
+            self.height,
+            self.elements.join("\n")
+        )
+    }
+}
+
+impl GraphicsEngine for SvgEngine {
+    fn clear(&mut self) -> Result<(), FlipClockError> {
+        self.elements.clear();
+        let screen = Rect::new(0, 0, self.width, self.height);
+        self.push_rect(
+            &screen,
+            0,
+            Some(to_color(self.settings.background_color)),
+            None,
+        );
+        Ok(())
+    }
+
+    fn present(&mut self) -> Result<(), FlipClockError> {
+        Ok(())
+    }
+
+    fn draw_rect(
+        &mut self,
+        rect: &Rect,
+        color: Color,
+        border_color: Option<Color>,
+        border_width: u32,
+        radius: i32,
+        filled: bool,
+    ) -> Result<(), FlipClockError> {
+        self.push_rect(
+            rect,
+            radius.max(0) as u32,
+            filled.then_some(color),
+            border_color.map(|border_color| (border_color, border_width)),
+        );
+        Ok(())
+    }
+
+    fn render_digit(
+        &mut self,
+        value: u32,
+        position: TimeDigitPosition,
+        rect: &Rect,
+        _animation: Option<AnimationState>,
+    ) -> Result<(), FlipClockError> {
+        self.render_digits(value, position, rect);
+        Ok(())
+    }
+
+    fn render_date(&mut self, rect: &Rect, text: &str) -> Result<(), FlipClockError> {
+        self.push_card(rect);
+        let size = self.font_size(FontRole::Label);
+        let (width, height) = self.fonts[&FontRole::Label].measure(text, size as f32);
+        let flap = Flap::centred(
+            rect,
+            width,
+            height,
+            FontRole::Label.vertical_offset(size, &self.settings),
+        );
+        self.copy_text(FontRole::Label, text, size, flap.src, flap.dst);
+        Ok(())
+    }
+
+    fn render_am_pm_indicator(
+        &mut self,
+        hour_rect: &Rect,
+        cards: &Rect,
+        is_pm: bool,
+    ) -> Result<(), FlipClockError> {
+        if self.settings.use_24hour {
+            return Ok(());
+        }
+        let size = self.font_size(FontRole::Indicator);
+        match self.settings.am_pm_style {
+            AmPmStyle::Text => {
+                let label = self.settings.am_pm_labels[is_pm as usize].clone();
+                let (width, height) = self.fonts[&FontRole::Indicator].measure(&label, size as f32);
+                if let Some((x, y)) =
+                    am_pm_position(&self.settings, hour_rect, cards, is_pm, width, height)
+                {
+                    let y = y + FontRole::Indicator.vertical_offset(size, &self.settings);
+                    let full = Rect::new(0, 0, width, height);
+                    self.copy_text(
+                        FontRole::Indicator,
+                        &label,
+                        size,
+                        full,
+                        Rect::new(x, y, width, height),
+                    );
+                }
+            }
+            AmPmStyle::Dot if is_pm => {
+                let dot = am_pm_dot_size(size);
+                if let Some((x, y)) =
+                    am_pm_position(&self.settings, hour_rect, cards, is_pm, dot, dot)
+                {
+                    let color = to_color(FontRole::Indicator.color(&self.settings));
+                    self.push_rect(&Rect::new(x, y, dot, dot), dot / 2, Some(color), None);
+                }
+            }
+            AmPmStyle::Dot => {}
+        }
+        Ok(())
+    }
+
+    fn handle_events(&mut self) -> Result<Vec<Action>, FlipClockError> {
+        Ok(Vec::new())
+    }
+
+    fn calculate_layout(&self) -> ClockLayout {
+        ClockLayout::calculate(self.width, self.height, &self.settings)
+    }
+
+    fn get_settings(&self) -> &ClockSettings {
+        &self.settings
+    }
+
+    fn update_settings(&mut self, settings: ClockSettings) -> Result<(), FlipClockError> {
+        if settings.font_changed(&self.settings) {
+            self.fonts = raster::load_fonts(&settings)?;
+        }
+        self.settings = settings;
+        Ok(())
+    }
+
+    /// Saves the document instead of pixels, whatever the extension of `path`.
+    fn save_screenshot(&mut self, path: &Path) -> Result<(), FlipClockError> {
+        let document = self.document();
+        export::write_atomically(path, |writer| writer.write_all(document.as_bytes()))
+    }
+}
+
+fn to_color(color: RgbColor) -> Color {
+    Color::rgb(color.r, color.g, color.b)
+}
+
+/// A `fill` or `stroke` attribute, with an opacity for translucent colors.
+fn paint(attribute: &str, color: Color) -> String {
+    let mut paint = format!(
+        r##" {}="#{:02x}{:02x}{:02x}""##,
+        attribute, color.r, color.g, color.b
+    );
+    if color.a < 255 {
+        paint.push_str(&format!(
+            r#" {}-opacity="{:.3}""#,
+            attribute,
+            color.a as f32 / 255.0
+        ));
+    }
+    paint
+}
+
+fn hex(color: RgbColor) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
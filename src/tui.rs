@@ -0,0 +1,231 @@
+use crate::error::FlipClockError;
+use crate::graphics_engine::Color;
+use crate::input::InputEvent;
+use crate::keybindings::{KeyCombo, Modifiers};
+use crate::raster::Canvas;
+use crate::software_engine::{Surface, SurfaceEvent};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+    KeyModifiers, MouseEventKind,
+};
+use crossterm::style::{self, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::io::{Stdout, Write};
+use std::time::Duration;
+
+/// Upper half block: the foreground color paints the top pixel of a cell,
+/// the background color the bottom one.
+const HALF_BLOCK: char = '\u{2580}';
+
+/// The terminal as a surface of `columns` x `2 * rows` pixels.
+pub struct Terminal {
+    stdout: Stdout,
+    truecolor: bool,
+    /// Top and bottom pixel of every cell on screen, to only redraw what changed.
+    cells: Vec<(Color, Color)>,
+}
+
+impl Terminal {
+    /// Switches the terminal to raw mode on the alternate screen until dropped.
+    pub fn open() -> Result<Self, FlipClockError> {
+        let mut stdout = std::io::stdout();
+        terminal::enable_raw_mode()
+            .map_err(|e| FlipClockError::io("Failed to set up the terminal", e))?;
+        execute!(
+            stdout,
+            EnterAlternateScreen,
+            Hide,
+            EnableMouseCapture,
+            Clear(ClearType::All)
+        )
+        .map_err(|e| FlipClockError::io("Failed to set up the terminal", e))?;
+
+        let truecolor =
+            std::env::var("COLORTERM").is_ok_and(|value| value == "truecolor" || value == "24bit");
+        Ok(Terminal {
+            stdout,
+            truecolor,
+            cells: Vec::new(),
+        })
+    }
+
+    fn color(&self, color: Color) -> style::Color {
+        if self.truecolor {
+            style::Color::Rgb {
+                r: color.r,
+                g: color.g,
+                b: color.b,
+            }
+        } else {
+            style::Color::AnsiValue(ansi_256(color))
+        }
+    }
+
+    fn key_event(key: KeyEvent) -> Option<SurfaceEvent> {
+        if key.kind != KeyEventKind::Press {
+            return None;
+        }
+        // Raw mode swallows SIGINT, Ctrl+C has to keep working.
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            return Some(SurfaceEvent::Quit);
+        }
+
+        let modifiers = Modifiers {
+            ctrl: key.modifiers.contains(KeyModifiers::CONTROL),
+            alt: key.modifiers.contains(KeyModifiers::ALT),
+            shift: key.modifiers.contains(KeyModifiers::SHIFT),
+            logo: key.modifiers.contains(KeyModifiers::SUPER),
+        };
+        key_name(key.code).map(|name| SurfaceEvent::Key(KeyCombo::new(&name, modifiers)))
+    }
+}
+
+impl Surface for Terminal {
+    fn size(&self) -> Result<(u32, u32), FlipClockError> {
+        let (columns, rows) = terminal::size()
+            .map_err(|e| FlipClockError::io("Failed to read the terminal size", e))?;
+        Ok((columns as u32, rows as u32 * 2))
+    }
+
+    fn present(&mut self, canvas: &Canvas) -> Result<(), FlipClockError> {
+        let columns = canvas.width();
+        let rows = canvas.height() / 2;
+        let cell_count = (columns * rows) as usize;
+        if self.cells.len() != cell_count {
+            // Size changed, nothing on screen can be trusted.
+            self.cells = vec![(Color::rgba(0, 0, 0, 0), Color::rgba(0, 0, 0, 0)); cell_count];
+        }
+
+        let mut cursor = None;
+        let mut colors = None;
+        for row in 0..rows {
+            for column in 0..columns {
+                let cell = (
+                    canvas.pixel(column, row * 2),
+                    canvas.pixel(column, row * 2 + 1),
+                );
+                let index = (row * columns + column) as usize;
+                if self.cells[index] == cell {
+                    continue;
+                }
+                self.cells[index] = cell;
+
+                if cursor != Some((column, row)) {
+                    queue!(self.stdout, MoveTo(column as u16, row as u16))
+                        .map_err(|e| FlipClockError::io("Failed to draw to the terminal", e))?;
+                }
+                if colors != Some(cell) {
+                    let (top, bottom) = (self.color(cell.0), self.color(cell.1));
+                    queue!(
+                        self.stdout,
+                        SetForegroundColor(top),
+                        SetBackgroundColor(bottom)
+                    )
+                    .map_err(|e| FlipClockError::io("Failed to draw to the terminal", e))?;
+                    colors = Some(cell);
+                }
+                queue!(self.stdout, Print(HALF_BLOCK))
+                    .map_err(|e| FlipClockError::io("Failed to draw to the terminal", e))?;
+                cursor = Some((column + 1, row));
+            }
+        }
+        self.stdout
+            .flush()
+            .map_err(|e| FlipClockError::io("Failed to draw to the terminal", e))
+    }
+
+    fn poll_events(&mut self) -> Result<Vec<SurfaceEvent>, FlipClockError> {
+        let mut events = Vec::new();
+        while event::poll(Duration::ZERO).map_err(read_error)? {
+            let event = match event::read().map_err(read_error)? {
+                Event::Key(key) => Self::key_event(key),
+                Event::Mouse(mouse) => match mouse.kind {
+                    MouseEventKind::Down(_) => Some(SurfaceEvent::Input(InputEvent::Button)),
+                    MouseEventKind::ScrollUp
+                    | MouseEventKind::ScrollDown
+                    | MouseEventKind::ScrollLeft
+                    | MouseEventKind::ScrollRight => Some(SurfaceEvent::Input(InputEvent::Wheel)),
+                    MouseEventKind::Moved | MouseEventKind::Drag(_) => {
+                        Some(SurfaceEvent::Input(InputEvent::Motion {
+                            x: mouse.column as i32,
+                            y: mouse.row as i32 * 2,
+                        }))
+                    }
+                    MouseEventKind::Up(_) => None,
+                },
+                // Delivered on SIGWINCH.
+                Event::Resize(..) => {
+                    self.cells.clear();
+                    queue!(self.stdout, ResetColor, Clear(ClearType::All))
+                        .map_err(|e| FlipClockError::io("Failed to draw to the terminal", e))?;
+                    Some(SurfaceEvent::Resized)
+                }
+                _ => None,
+            };
+            events.extend(event);
+        }
+        Ok(events)
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = execute!(
+            self.stdout,
+            DisableMouseCapture,
+            ResetColor,
+            Show,
+            LeaveAlternateScreen
+        );
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// SDL's name for a key, so `[keybindings]` work the same in a terminal.
+fn key_name(code: KeyCode) -> Option<String> {
+    let name = match code {
+        KeyCode::Esc => "Escape",
+        KeyCode::Enter => "Return",
+        KeyCode::Char(' ') => "Space",
+        KeyCode::Char(c) => return Some(c.to_string()),
+        KeyCode::F(number) => return Some(format!("F{}", number)),
+        KeyCode::Left => "Left",
+        KeyCode::Right => "Right",
+        KeyCode::Up => "Up",
+        KeyCode::Down => "Down",
+        KeyCode::Backspace => "Backspace",
+        KeyCode::Tab => "Tab",
+        KeyCode::Delete => "Delete",
+        KeyCode::Insert => "Insert",
+        KeyCode::Home => "Home",
+        KeyCode::End => "End",
+        KeyCode::PageUp => "PageUp",
+        KeyCode::PageDown => "PageDown",
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
+fn read_error(e: std::io::Error) -> FlipClockError {
+    FlipClockError::io("Failed to read from the terminal", e)
+}
+
+/// Closest color of the xterm 256 color palette, for terminals without truecolor.
+fn ansi_256(color: Color) -> u8 {
+    let (r, g, b) = (color.r as i32, color.g as i32, color.b as i32);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    if max - min < 10 {
+        // Grays get the finer grayscale ramp, 232 to 255 covering 8 to 238.
+        let gray = (r + g + b) / 3;
+        return match gray {
+            0..8 => 16,
+            239.. => 231,
+            _ => 232 + ((gray - 8) / 10) as u8,
+        };
+    }
+    let level = |value: i32| ((value * 5 + 127) / 255) as u8;
+    16 + 36 * level(r) + 6 * level(g) + level(b)
+}
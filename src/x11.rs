@@ -0,0 +1,156 @@
+#[cfg(all(unix, not(target_os = "macos")))]
+mod imp {
+    use crate::error::FlipClockError;
+    use std::ffi::CString;
+    use std::os::raw::c_long;
+    use x11_dl::{xfixes, xlib};
+
+    /// `ShapeInput` from the X Shape extension.
+    const SHAPE_INPUT: i32 = 2;
+
+    #[allow(clippy::unnecessary_cast)] // `Window` is a `c_ulong`, only 32 bits on some targets.
+    pub fn root_window() -> Result<u64, FlipClockError> {
+        with_display(|xlib, display| Ok(unsafe { (xlib.XDefaultRootWindow)(display) } as u64))
+    }
+
+    /// A 32 bit TrueColor visual, whose alpha channel a compositor blends with the desktop.
+    #[allow(clippy::unnecessary_cast)]
+    pub fn argb_visual_id() -> Option<u64> {
+        with_display(|xlib, display| {
+            let mut info: xlib::XVisualInfo = unsafe { std::mem::zeroed() };
+            let found = unsafe {
+                let screen = (xlib.XDefaultScreen)(display);
+                (xlib.XMatchVisualInfo)(display, screen, 32, xlib::TrueColor, &mut info)
+            };
+            if found == 0 {
+                return Err(FlipClockError::Video("No 32 bit visual".to_string()));
+            }
+            Ok(info.visualid as u64)
+        })
+        .ok()
+    }
+
+    /// Whether a compositing manager owns the `_NET_WM_CM_S<screen>` selection.
+    pub fn compositor_running() -> bool {
+        with_display(|xlib, display| {
+            let owner = unsafe {
+                let screen = (xlib.XDefaultScreen)(display);
+                let atom = intern_atom(xlib, display, &format!("_NET_WM_CM_S{}", screen))?;
+                (xlib.XGetSelectionOwner)(display, atom)
+            };
+            Ok(owner != 0)
+        })
+        .unwrap_or(false)
+    }
+
+    /// Adds an EWMH state such as `_NET_WM_STATE_BELOW` to a mapped window,
+    /// which has to go through the window manager as a client message.
+    pub fn add_window_state(window: u64, state: &str) -> Result<(), FlipClockError> {
+        with_display(|xlib, display| {
+            let mut data = xlib::ClientMessageData::new();
+            data.set_long(0, 1); // _NET_WM_STATE_ADD
+            data.set_long(1, intern_atom(xlib, display, state)? as c_long);
+            data.set_long(3, 1); // Request comes from a normal application.
+
+            let mut event = xlib::XEvent {
+                client_message: xlib::XClientMessageEvent {
+                    type_: xlib::ClientMessage,
+                    serial: 0,
+                    send_event: xlib::True,
+                    display,
+                    window: window as xlib::Window,
+                    message_type: intern_atom(xlib, display, "_NET_WM_STATE")?,
+                    format: 32,
+                    data,
+                },
+            };
+            unsafe {
+                (xlib.XSendEvent)(
+                    display,
+                    (xlib.XDefaultRootWindow)(display),
+                    xlib::False,
+                    xlib::SubstructureRedirectMask | xlib::SubstructureNotifyMask,
+                    &mut event,
+                );
+            }
+            Ok(())
+        })
+    }
+
+    /// Gives the window an empty input shape, so clicks fall through to whatever is below.
+    pub fn set_click_through(window: u64) -> Result<(), FlipClockError> {
+        let xfixes = xfixes::Xlib::open().map_err(|e| FlipClockError::Video(e.to_string()))?;
+        with_display(|_, display| {
+            unsafe {
+                let region = (xfixes.XFixesCreateRegion)(display, std::ptr::null_mut(), 0);
+                (xfixes.XFixesSetWindowShapeRegion)(
+                    display,
+                    window as xlib::Window,
+                    SHAPE_INPUT,
+                    0,
+                    0,
+                    region,
+                );
+                (xfixes.XFixesDestroyRegion)(display, region);
+            }
+            Ok(())
+        })
+    }
+
+    fn intern_atom(
+        xlib: &xlib::Xlib,
+        display: *mut xlib::Display,
+        name: &str,
+    ) -> Result<xlib::Atom, FlipClockError> {
+        let name = CString::new(name).map_err(|e| FlipClockError::Video(e.to_string()))?;
+        Ok(unsafe { (xlib.XInternAtom)(display, name.as_ptr(), xlib::False) })
+    }
+
+    fn with_display<T>(
+        f: impl FnOnce(&xlib::Xlib, *mut xlib::Display) -> Result<T, FlipClockError>,
+    ) -> Result<T, FlipClockError> {
+        let xlib = xlib::Xlib::open().map_err(|e| FlipClockError::Video(e.to_string()))?;
+        let display = unsafe { (xlib.XOpenDisplay)(std::ptr::null()) };
+        if display.is_null() {
+            return Err(FlipClockError::Video(
+                "Failed to open X display".to_string(),
+            ));
+        }
+        let result = f(&xlib, display);
+        unsafe { (xlib.XCloseDisplay)(display) };
+        result
+    }
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+mod imp {
+    use crate::error::FlipClockError;
+
+    pub fn root_window() -> Result<u64, FlipClockError> {
+        Err(FlipClockError::Video(
+            "Screensaver mode is only supported on X11".to_string(),
+        ))
+    }
+
+    pub fn argb_visual_id() -> Option<u64> {
+        None
+    }
+
+    pub fn compositor_running() -> bool {
+        false
+    }
+
+    pub fn add_window_state(_window: u64, _state: &str) -> Result<(), FlipClockError> {
+        Err(FlipClockError::Video(
+            "Window states are only supported on X11".to_string(),
+        ))
+    }
+
+    pub fn set_click_through(_window: u64) -> Result<(), FlipClockError> {
+        Err(FlipClockError::Video(
+            "Click-through is only supported on X11".to_string(),
+        ))
+    }
+}
+
+pub use imp::*;